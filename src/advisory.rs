@@ -0,0 +1,173 @@
+use crate::error::{CcuError, Result};
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A RustSec advisory, as stored in `crates/<name>/RUSTSEC-*.md` of the advisory-db
+#[derive(Debug, Clone)]
+pub struct Advisory {
+    pub id: String,
+    pub package: String,
+    pub title: String,
+    pub date: String,
+    pub url: Option<String>,
+    /// Other identifiers, e.g. CVE numbers
+    pub aliases: Vec<String>,
+    /// "unmaintained", "unsound", "notice"...; `None` for vulnerabilities
+    pub informational: Option<String>,
+    pub withdrawn: bool,
+    /// Versions with the fix
+    pub patched: Vec<VersionReq>,
+    /// Versions that never had the problem
+    pub unaffected: Vec<VersionReq>,
+}
+
+/// The TOML front matter of an advisory
+#[derive(Deserialize)]
+struct AdvisoryFile {
+    advisory: AdvisoryMetadata,
+    #[serde(default)]
+    versions: AdvisoryVersions,
+}
+
+#[derive(Deserialize)]
+struct AdvisoryMetadata {
+    id: String,
+    package: String,
+    /// Only in the older all-TOML format; Markdown advisories use a heading
+    #[serde(default)]
+    title: Option<String>,
+    date: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    informational: Option<String>,
+    #[serde(default)]
+    withdrawn: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct AdvisoryVersions {
+    #[serde(default)]
+    patched: Vec<VersionReq>,
+    #[serde(default)]
+    unaffected: Vec<VersionReq>,
+}
+
+impl Advisory {
+    /// Parse an advisory file: Markdown with a ```toml front matter block and
+    /// a `# Title` heading, or plain TOML
+    pub fn parse(content: &str) -> Result<Self> {
+        let (front_matter, body) = match content.trim_start().strip_prefix("```toml") {
+            Some(rest) => rest
+                .split_once("\n```")
+                .ok_or_else(|| CcuError::Advisory("unterminated front matter".to_string()))?,
+            None => (content, ""),
+        };
+
+        let file: AdvisoryFile = toml::from_str(front_matter).map_err(|e| CcuError::Advisory(e.to_string()))?;
+        let heading = body
+            .lines()
+            .find_map(|line| line.strip_prefix("# "))
+            .map(|title| title.trim().to_string());
+        let metadata = file.advisory;
+
+        Ok(Self {
+            title: heading.or(metadata.title).unwrap_or_default(),
+            id: metadata.id,
+            package: metadata.package,
+            date: metadata.date,
+            url: metadata.url,
+            aliases: metadata.aliases,
+            informational: metadata.informational,
+            withdrawn: metadata.withdrawn.is_some(),
+            patched: file.versions.patched,
+            unaffected: file.versions.unaffected,
+        })
+    }
+
+    /// Whether a version has the problem
+    pub fn affects(&self, version: &Version) -> bool {
+        !self.withdrawn && !self.patched.iter().chain(&self.unaffected).any(|req| req.matches(version))
+    }
+
+    /// Security vulnerabilities, as opposed to informational notices
+    pub fn is_vulnerability(&self) -> bool {
+        self.informational.is_none()
+    }
+}
+
+/// A local clone of the RustSec advisory-db
+#[derive(Debug, Default)]
+pub struct AdvisoryDb {
+    /// Crate name → advisories
+    advisories: HashMap<String, Vec<Advisory>>,
+    /// Advisory files that couldn't be parsed, e.g. ones using a newer format
+    pub unparsable: Vec<PathBuf>,
+}
+
+impl AdvisoryDb {
+    /// Where `cargo audit` keeps its clone: `$CARGO_HOME/advisory-db`
+    pub fn default_path() -> Option<PathBuf> {
+        Some(crate::cargo::home()?.join("advisory-db"))
+    }
+
+    /// Load every advisory under `<path>/crates`, skipping files that can't be parsed
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let crates = path.as_ref().join("crates");
+        let read_dir = |dir: &Path| {
+            std::fs::read_dir(dir).map_err(|e| CcuError::Advisory(format!("{}: {}", dir.display(), e)))
+        };
+
+        let mut db = Self::default();
+        for crate_dir in read_dir(&crates)? {
+            let crate_dir = crate_dir?.path();
+            if !crate_dir.is_dir() {
+                continue;
+            }
+
+            for file in read_dir(&crate_dir)? {
+                let file = file?.path();
+                if !matches!(file.extension().and_then(|ext| ext.to_str()), Some("md" | "toml")) {
+                    continue;
+                }
+
+                let content = std::fs::read_to_string(&file)?;
+                match Advisory::parse(&content) {
+                    Ok(advisory) => db.advisories.entry(advisory.package.clone()).or_default().push(advisory),
+                    Err(_) => db.unparsable.push(file),
+                }
+            }
+        }
+
+        for advisories in db.advisories.values_mut() {
+            advisories.sort_by(|a, b| a.id.cmp(&b.id));
+        }
+        db.unparsable.sort();
+        Ok(db)
+    }
+
+    /// Advisories filed against a crate
+    pub fn for_crate(&self, name: &str) -> &[Advisory] {
+        self.advisories.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Informational advisories (unmaintained, unsound...) affecting a version of a crate
+    pub fn notices(&self, name: &str, version: &Version) -> Vec<&Advisory> {
+        self.for_crate(name)
+            .iter()
+            .filter(|advisory| !advisory.is_vulnerability() && advisory.affects(version))
+            .collect()
+    }
+
+    /// Vulnerabilities affecting a version of a crate
+    pub fn vulnerabilities(&self, name: &str, version: &Version) -> Vec<&Advisory> {
+        self.for_crate(name)
+            .iter()
+            .filter(|advisory| advisory.is_vulnerability() && advisory.affects(version))
+            .collect()
+    }
+}
@@ -0,0 +1,79 @@
+use semver::Version;
+use serde::Serialize;
+
+/// How an upgrade changes a version under Cargo's compatibility rules: the
+/// leftmost non-zero component is the "major" one, so 0.21 → 0.22 and
+/// 0.0.3 → 0.0.4 are breaking just like 1.x → 2.0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum BumpKind {
+    /// Outside the current requirement's compatible range
+    #[value(alias = "major")]
+    Breaking,
+    /// Compatible release with a new minor version (1.2 → 1.3)
+    #[value(alias = "minor")]
+    CompatibleFeature,
+    /// Compatible release with a new patch version (1.2.3 → 1.2.4, 0.2.3 → 0.2.4)
+    #[value(alias = "patch")]
+    CompatibleFix,
+    /// Compatible prerelease (1.2.3 → 1.3.0-rc.1)
+    #[value(alias = "pre")]
+    Prerelease,
+}
+
+impl BumpKind {
+    /// Classify the change from `current` to `target`
+    pub fn of(current: &Version, target: &Version) -> Self {
+        if compatibility_key(current) != compatibility_key(target) {
+            Self::Breaking
+        } else if !target.pre.is_empty() {
+            Self::Prerelease
+        } else if current.major > 0 && target.minor != current.minor {
+            Self::CompatibleFeature
+        } else {
+            Self::CompatibleFix
+        }
+    }
+}
+
+/// The components Cargo requires to match for two versions to be compatible
+pub fn compatibility_key(version: &Version) -> (u64, u64, u64) {
+    match version {
+        Version { major: 0, minor: 0, patch, .. } => (0, 0, *patch),
+        Version { major: 0, minor, .. } => (0, *minor, 0),
+        Version { major, .. } => (*major, 0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bump_matrix() {
+        use BumpKind::*;
+
+        let cases = [
+            // (current, target, expected)
+            ("1.2.3", "2.0.0", Breaking),
+            ("1.2.3", "1.3.0", CompatibleFeature),
+            ("1.2.3", "1.2.4", CompatibleFix),
+            ("1.2.3", "1.3.0-rc.1", Prerelease),
+            ("1.2.3", "2.0.0-rc.1", Breaking),
+            ("1.0.0-rc.1", "1.0.0", CompatibleFix),
+            ("1.0.0-rc.1", "1.0.0-rc.2", Prerelease),
+            ("0.21.0", "0.22.0", Breaking),
+            ("0.21.0", "0.21.4", CompatibleFix),
+            ("0.21.0", "1.0.0", Breaking),
+            ("0.0.3", "0.0.4", Breaking),
+            ("0.0.3", "0.1.0", Breaking),
+            ("0.0.3", "0.0.3", CompatibleFix),
+        ];
+
+        for (current, target, expected) in cases {
+            let current = Version::parse(current).unwrap();
+            let target = Version::parse(target).unwrap();
+            assert_eq!(BumpKind::of(&current, &target), expected, "{} → {}", current, target);
+        }
+    }
+}
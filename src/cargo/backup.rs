@@ -0,0 +1,142 @@
+use crate::cargo::lockfile::Lockfile;
+use crate::error::{CcuError, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Index file recording where each backed-up file came from
+const INDEX_FILE: &str = "backup.json";
+
+/// Copies of the manifest and lockfile taken before an upgrade run
+#[derive(Debug)]
+pub struct Backup {
+    pub dir: PathBuf,
+    /// Canonical path of the manifest the backup was taken for
+    pub manifest: PathBuf,
+    pub files: Vec<BackupFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// Absolute path of the original file
+    pub original: PathBuf,
+    /// File name within the backup directory
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BackupIndex {
+    manifest: PathBuf,
+    files: Vec<BackupFile>,
+}
+
+impl Backup {
+    /// Directory holding backups for a manifest: `target/ccu-backups` at the
+    /// workspace root (where Cargo.lock lives), or `$CARGO_TARGET_DIR/ccu-backups`
+    pub fn root_for_manifest<P: AsRef<Path>>(manifest_path: P) -> Result<PathBuf> {
+        if let Some(target_dir) = std::env::var_os("CARGO_TARGET_DIR") {
+            return Ok(PathBuf::from(target_dir).join("ccu-backups"));
+        }
+
+        let manifest = std::fs::canonicalize(&manifest_path)?;
+        let root = match Lockfile::find_for_manifest(&manifest) {
+            Some(lockfile) => lockfile.parent().map(Path::to_path_buf),
+            None => manifest.parent().map(Path::to_path_buf),
+        }
+        .unwrap_or_default();
+
+        Ok(root.join("target").join("ccu-backups"))
+    }
+
+    /// Copy the manifest and its lockfile into a new timestamped backup
+    pub fn create<P: AsRef<Path>>(manifest_path: P) -> Result<Self> {
+        let manifest = std::fs::canonicalize(&manifest_path)?;
+        let mut originals = vec![manifest.clone()];
+        originals.extend(Lockfile::find_for_manifest(&manifest));
+
+        // Workspace members share a backup root, so two runs can start in the same millisecond
+        let root = Self::root_for_manifest(&manifest)?;
+        std::fs::create_dir_all(&root)?;
+        let timestamp = Utc::now().format("%Y%m%d-%H%M%S-%3f").to_string();
+        let mut dir = root.join(&timestamp);
+        let mut attempt = 0;
+        while let Err(e) = std::fs::create_dir(&dir) {
+            if e.kind() != std::io::ErrorKind::AlreadyExists {
+                return Err(e.into());
+            }
+            attempt += 1;
+            dir = root.join(format!("{}-{}", timestamp, attempt));
+        }
+
+        let mut files = Vec::new();
+        for (i, original) in originals.into_iter().enumerate() {
+            let file_name = original.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            let name = format!("{}-{}", i, file_name);
+            std::fs::copy(&original, dir.join(&name))?;
+            files.push(BackupFile { original, name });
+        }
+
+        let index = serde_json::to_string_pretty(&BackupIndex { manifest, files })
+            .map_err(|e| CcuError::Backup(format!("Failed to write backup index: {}", e)))?;
+        std::fs::write(dir.join(INDEX_FILE), index)?;
+
+        Self::load(dir)
+    }
+
+    /// Find the most recent backup for a manifest, skipping those of other
+    /// workspace members sharing the backup root
+    pub fn latest<P: AsRef<Path>>(manifest_path: P) -> Result<Option<Self>> {
+        let manifest = std::fs::canonicalize(&manifest_path)?;
+        let root = Self::root_for_manifest(&manifest)?;
+        let Ok(entries) = std::fs::read_dir(&root) else {
+            return Ok(None);
+        };
+
+        // Timestamps sort lexicographically, so the last directory is the newest
+        let mut dirs: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.join(INDEX_FILE).is_file())
+            .collect();
+        dirs.sort();
+
+        for dir in dirs.into_iter().rev() {
+            let backup = Self::load(dir)?;
+            if backup.manifest == manifest {
+                return Ok(Some(backup));
+            }
+        }
+        Ok(None)
+    }
+
+    fn load(dir: PathBuf) -> Result<Self> {
+        let content = std::fs::read_to_string(dir.join(INDEX_FILE))?;
+        let index: BackupIndex = serde_json::from_str(&content)
+            .map_err(|e| CcuError::Backup(format!("Invalid backup index in {}: {}", dir.display(), e)))?;
+
+        Ok(Self {
+            dir,
+            manifest: index.manifest,
+            files: index.files,
+        })
+    }
+
+    /// Put every backed-up file back in place, then delete the backup so the
+    /// next restore goes one run further back
+    pub fn restore(self) -> Result<Vec<PathBuf>> {
+        let mut restored = Vec::new();
+        for file in &self.files {
+            let content = std::fs::read_to_string(self.dir.join(&file.name))?;
+            crate::fs::write_atomic(&file.original, &content)?;
+            restored.push(file.original.clone());
+        }
+
+        self.discard()?;
+        Ok(restored)
+    }
+
+    /// Delete the backup, e.g. when the run it was taken for wrote nothing
+    pub fn discard(self) -> Result<()> {
+        std::fs::remove_dir_all(&self.dir)?;
+        Ok(())
+    }
+}
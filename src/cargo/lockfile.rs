@@ -0,0 +1,216 @@
+use crate::error::{CcuError, Result};
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A parsed Cargo.lock
+#[derive(Debug, Default, Deserialize)]
+pub struct Lockfile {
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+    #[serde(default)]
+    pub patch: LockedPatches,
+}
+
+/// The `[patch]` table Cargo writes to the lockfile
+#[derive(Debug, Default, Deserialize)]
+pub struct LockedPatches {
+    /// Patches that didn't match any dependency
+    #[serde(default)]
+    pub unused: Vec<LockedPackage>,
+}
+
+/// A `[[package]]` entry in Cargo.lock
+#[derive(Debug, Clone, Deserialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    pub source: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+impl LockedPackage {
+    /// Whether the package comes from a registry, as opposed to a path, git
+    /// repository or workspace member
+    pub fn is_registry(&self) -> bool {
+        self.source
+            .as_deref()
+            .is_some_and(|source| source.starts_with("registry+") || source.starts_with("sparse+"))
+    }
+
+    /// Workspace members and path dependencies have no source
+    pub fn is_local(&self) -> bool {
+        self.source.is_none()
+    }
+}
+
+impl Lockfile {
+    /// Parse a Cargo.lock file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| CcuError::Lockfile(format!("Failed to read file: {}", e)))?;
+
+        toml::from_str(&content)
+            .map_err(|e| CcuError::Lockfile(format!("Failed to parse TOML: {}", e)))
+    }
+
+    /// Find the Cargo.lock used by a manifest: next to it, or at the
+    /// workspace root in one of its parent directories
+    pub fn find_for_manifest<P: AsRef<Path>>(manifest_path: P) -> Option<PathBuf> {
+        let manifest = std::fs::canonicalize(manifest_path).ok()?;
+        manifest
+            .parent()?
+            .ancestors()
+            .map(|dir| dir.join("Cargo.lock"))
+            .find(|path| path.exists())
+    }
+
+    /// Commit a git dependency is locked to, from a source like
+    /// `git+https://github.com/foo/bar?branch=main#0123abcd`
+    pub fn locked_git_commit(&self, name: &str, url: &str) -> Option<&str> {
+        let url = url.trim_end_matches('/').trim_end_matches(".git");
+        self.packages
+            .iter()
+            .filter(|package| package.name == name)
+            .filter_map(|package| package.source.as_deref()?.strip_prefix("git+"))
+            .find(|source| {
+                let source_url = source.split(['?', '#']).next().unwrap_or_default();
+                source_url.trim_end_matches('/').trim_end_matches(".git") == url
+            })
+            .and_then(|source| source.rsplit_once('#'))
+            .map(|(_, commit)| commit)
+    }
+
+    /// Version of a crate that was resolved from somewhere other than a
+    /// registry, i.e. the version a `[patch]` or `[replace]` entry provides
+    pub fn patched_version(&self, name: &str) -> Option<&str> {
+        self.packages
+            .iter()
+            .filter(|package| package.name == name)
+            .find(|package| !package.is_registry())
+            .map(|package| package.version.as_str())
+    }
+
+    /// Registry version of a crate locked for a dependency with this requirement
+    pub fn locked_version(&self, name: &str, requirement: &VersionReq) -> Option<Version> {
+        self.packages
+            .iter()
+            .filter(|package| package.name == name && package.is_registry())
+            .filter_map(|package| Version::parse(&package.version).ok())
+            .filter(|version| requirement.matches(version))
+            .max()
+    }
+
+    /// Dependency graph of the locked packages
+    pub fn graph(&self) -> LockGraph<'_> {
+        LockGraph::new(self)
+    }
+
+    /// The patch Cargo recorded as unused for this crate, if any
+    pub fn unused_patch(&self, name: &str) -> Option<&LockedPackage> {
+        self.patch.unused.iter().find(|package| package.name == name)
+    }
+}
+
+/// The packages of a lockfile with their dependency edges resolved, for
+/// walking from a package to what pulls it in
+pub struct LockGraph<'a> {
+    lockfile: &'a Lockfile,
+    /// Indices of the packages depending on each package
+    dependents: Vec<Vec<usize>>,
+}
+
+impl<'a> LockGraph<'a> {
+    fn new(lockfile: &'a Lockfile) -> Self {
+        let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, package) in lockfile.packages.iter().enumerate() {
+            by_name.entry(package.name.as_str()).or_default().push(i);
+        }
+
+        // Entries name just the crate, or add the version and then the source
+        // when the lockfile holds several copies
+        let mut dependents = vec![Vec::new(); lockfile.packages.len()];
+        for (i, package) in lockfile.packages.iter().enumerate() {
+            for entry in &package.dependencies {
+                let mut parts = entry.splitn(3, ' ');
+                let name = parts.next().unwrap_or_default();
+                let version = parts.next();
+                let source = parts.next().map(|source| source.trim_start_matches('(').trim_end_matches(')'));
+
+                let found = by_name.get(name).into_iter().flatten().find(|&&candidate| {
+                    let candidate = &lockfile.packages[candidate];
+                    version.is_none_or(|version| candidate.version == version)
+                        && source.is_none_or(|source| candidate.source.as_deref() == Some(source))
+                });
+                if let Some(&dependency) = found {
+                    dependents[dependency].push(i);
+                }
+            }
+        }
+
+        Self { lockfile, dependents }
+    }
+
+    fn index_of(&self, package: &LockedPackage) -> Option<usize> {
+        self.lockfile.packages.iter().position(|candidate| std::ptr::eq(candidate, package))
+    }
+
+    /// Packages that depend on a package of this lockfile
+    pub fn dependents_of(&self, package: &LockedPackage) -> Vec<&'a LockedPackage> {
+        self.index_of(package)
+            .map(|i| self.dependents[i].iter().map(|&j| &self.lockfile.packages[j]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether a workspace member or path dependency depends on the package directly
+    pub fn is_direct(&self, package: &LockedPackage) -> bool {
+        self.dependents_of(package).iter().any(|dependent| dependent.is_local())
+    }
+
+    /// Direct dependencies whose dependency trees pull in a package
+    pub fn direct_dependents(&self, package: &LockedPackage) -> Vec<&'a LockedPackage> {
+        let Some(start) = self.index_of(package) else {
+            return Vec::new();
+        };
+        let mut seen = vec![false; self.dependents.len()];
+        seen[start] = true;
+        let mut queue = vec![start];
+        let mut direct = Vec::new();
+
+        while let Some(current) = queue.pop() {
+            for &dependent in &self.dependents[current] {
+                if std::mem::replace(&mut seen[dependent], true) {
+                    continue;
+                }
+
+                let package = &self.lockfile.packages[dependent];
+                if package.is_local() {
+                    continue;
+                }
+                if self.dependents[dependent]
+                    .iter()
+                    .any(|&j| self.lockfile.packages[j].is_local())
+                {
+                    direct.push(package);
+                }
+                queue.push(dependent);
+            }
+        }
+
+        direct.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+        direct
+    }
+
+    /// Direct dependencies that pull in a package, starting with the package itself if it's direct
+    pub fn direct_ancestors(&self, package: &LockedPackage) -> Vec<&'a LockedPackage> {
+        let mut ancestors = self.direct_dependents(package);
+        if self.is_direct(package) {
+            if let Some(i) = self.index_of(package) {
+                ancestors.insert(0, &self.lockfile.packages[i]);
+            }
+        }
+        ancestors
+    }
+}
@@ -1,3 +1,52 @@
+pub mod backup;
+pub mod lockfile;
 pub mod parser;
 
+pub use lockfile::Lockfile;
 pub use parser::CargoTomlParser;
+
+use semver::Version;
+use std::path::{Path, PathBuf};
+
+/// Cargo's home directory: `$CARGO_HOME`, or `~/.cargo`
+pub fn home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))
+}
+
+/// The version a requirement is written against, e.g. 1.2.0 for "^1.2" and
+/// 1.0.0-beta.3 for "=1.0-beta.3"
+pub fn requirement_version(requirement: &str) -> Option<Version> {
+    Version::parse(&normalize_version(&strip_version_operator(requirement))).ok()
+}
+
+/// Remove a leading requirement operator, e.g. "^1.2" → "1.2"
+pub fn strip_version_operator(version: &str) -> String {
+    let version = version.trim();
+    if version.starts_with("^") || version.starts_with("~") || version.starts_with("=") {
+        version[1..].to_string()
+    } else if version.starts_with(">=") || version.starts_with("<=") {
+        version[2..].to_string()
+    } else if version.starts_with('>') || version.starts_with('<') {
+        version[1..].to_string()
+    } else {
+        version.to_string()
+    }
+}
+
+/// Normalize version string to semver format (major.minor.patch)
+/// Cargo allows shorthand like "0.21" or "2", but semver requires full format
+/// Prerelease suffixes are kept, so "1.0-beta.3" → "1.0.0-beta.3"
+pub fn normalize_version(version: &str) -> String {
+    let (core, suffix) = match version.find(['-', '+']) {
+        Some(i) => version.split_at(i),
+        None => (version, ""),
+    };
+    let parts: Vec<&str> = core.split('.').collect();
+    match parts.len() {
+        1 => format!("{}.0.0{}", parts[0], suffix),  // "2" → "2.0.0"
+        2 => format!("{}.{}.0{}", parts[0], parts[1], suffix),  // "0.21" → "0.21.0"
+        _ => version.to_string(),  // Already complete or invalid
+    }
+}
@@ -1,5 +1,7 @@
 use crate::error::{CcuError, Result};
+use semver::Version;
 use std::path::Path;
+use std::str::FromStr;
 use toml_edit::Document;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -7,28 +9,156 @@ pub enum DependencySection {
     Dependencies,
     DevDependencies,
     BuildDependencies,
+    WorkspaceDependencies,
 }
 
 impl DependencySection {
+    /// Every section dependencies are read from
+    pub const ALL: [DependencySection; 4] = [
+        Self::Dependencies,
+        Self::DevDependencies,
+        Self::BuildDependencies,
+        Self::WorkspaceDependencies,
+    ];
+
     pub fn as_str(&self) -> &str {
         match self {
             Self::Dependencies => "dependencies",
             Self::DevDependencies => "dev-dependencies",
             Self::BuildDependencies => "build-dependencies",
+            Self::WorkspaceDependencies => "workspace.dependencies",
+        }
+    }
+}
+
+impl FromStr for DependencySection {
+    type Err = String;
+
+    /// Accepts the section name or its short alias ("prod", "dev", "build", "workspace")
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "dependencies" | "prod" => Ok(Self::Dependencies),
+            "dev-dependencies" | "dev" => Ok(Self::DevDependencies),
+            "build-dependencies" | "build" => Ok(Self::BuildDependencies),
+            "workspace.dependencies" | "workspace" => Ok(Self::WorkspaceDependencies),
+            _ => Err(format!("Unknown dependency section '{}'", s)),
         }
     }
 }
 
+impl<'de> serde::Deserialize<'de> for DependencySection {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Dependency {
     pub name: String,
     pub version: String,  // e.g., "^1.0.0", "1.0", ">=0.5"
     pub section: DependencySection,
+    pub optional: bool,
+    /// Registry crate name when the dependency is renamed (`package = "..."`)
+    pub package: Option<String>,
+    /// Features enabled with `features = [...]`
+    pub features: Vec<String>,
+    /// Whether the crate's default features are enabled
+    pub default_features: bool,
+    /// Local directory of a `path` dependency that also declares a version
+    pub path: Option<String>,
+}
+
+impl Dependency {
+    /// Name of the crate in the registry, which differs from `name` for renamed dependencies
+    pub fn crate_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// A dependency fetched from a git repository
+#[derive(Debug, Clone)]
+pub struct GitDependency {
+    pub name: String,
+    pub url: String,
+    pub reference: GitReference,
+    pub section: DependencySection,
+    pub optional: bool,
+}
+
+/// A dependency on a crate in a local directory
+#[derive(Debug, Clone)]
+pub struct PathDependency {
+    pub name: String,
+    pub path: String,
+    /// Requirement used when the crate is published, if any
+    pub version: Option<String>,
+    pub section: DependencySection,
+    pub optional: bool,
+}
+
+/// An entry in `[patch.<source>]` or `[replace]`
+#[derive(Debug, Clone)]
+pub struct Patch {
+    pub name: String,
+    /// Source being patched: "crates-io", another registry or a URL, or
+    /// "replace" for `[replace]` entries
+    pub source: String,
+    /// Crate name when the patch is renamed (`package = "..."`)
+    pub package: Option<String>,
+    /// Version a `[replace]` entry replaces, from its "name:version" key
+    pub replaces: Option<String>,
+}
+
+impl Patch {
+    /// Name of the crate being patched
+    pub fn crate_name(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Whether the patched crate comes from crates.io, so we can look up upstream releases
+    pub fn patches_crates_io(&self) -> bool {
+        self.source == "crates-io" || self.source == "replace"
+    }
+}
+
+/// Which commit of a git dependency the manifest asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitReference {
+    Tag(String),
+    Rev(String),
+    Branch(String),
+    /// No tag, rev or branch: the remote's default branch
+    DefaultBranch,
 }
 
 pub struct CargoTomlParser {
     document: Document,
     path: String,
+    /// Whether the file uses CRLF line endings, which toml_edit normalizes away
+    crlf: bool,
+    /// Hash of the file as it was read, to detect edits made before `save()`
+    original_hash: u64,
+    /// Edits applied so far, replayed if the file changed on disk meanwhile
+    edits: Vec<Edit>,
+}
+
+/// Which string of a dependency entry an edit replaces
+#[derive(Debug, Clone, Copy)]
+enum EditField {
+    Version,
+    GitTag,
+}
+
+/// A single value replacement, kept so it can be re-applied to a fresh copy
+/// of the manifest
+#[derive(Debug, Clone)]
+struct Edit {
+    name: String,
+    section: DependencySection,
+    field: EditField,
+    old: String,
+    new: String,
 }
 
 impl CargoTomlParser {
@@ -38,13 +168,76 @@ impl CargoTomlParser {
         let content = std::fs::read_to_string(&path)
             .map_err(|e| CcuError::CargoTomlParse(format!("Failed to read file: {}", e)))?;
 
+        Self::parse(&content, path_str)
+    }
+
+    fn parse(content: &str, path: String) -> Result<Self> {
         let document = content
             .parse::<Document>()
             .map_err(|e| CcuError::CargoTomlParse(format!("Failed to parse TOML: {}", e)))?;
 
         Ok(Self {
             document,
-            path: path_str,
+            path,
+            crlf: content.contains("\r\n"),
+            original_hash: hash_contents(content),
+            edits: Vec::new(),
+        })
+    }
+
+    /// Get the minimum supported Rust version from `package.rust-version`,
+    /// falling back to `workspace.package.rust-version` for virtual manifests
+    /// and members that inherit it
+    pub fn rust_version(&self) -> Option<Version> {
+        let rust_version = match self.document.get("package") {
+            Some(_) => self.package_field("rust-version")?,
+            None => Self::workspace_package_field(&self.document, "rust-version")?,
+        };
+        parse_rust_version(&rust_version)
+    }
+
+    /// Get the crate's own `package.name`
+    pub fn package_name(&self) -> Option<String> {
+        self.package_field("name")
+    }
+
+    /// Get the crate's own `package.version`
+    pub fn package_version(&self) -> Option<Version> {
+        Version::parse(&self.package_field("version")?).ok()
+    }
+
+    /// Read a string field from `[package]`, following `field.workspace = true`
+    /// to the workspace root
+    fn package_field(&self, key: &str) -> Option<String> {
+        let item = self.document.get("package")?.get(key)?;
+        match item.as_str() {
+            Some(s) => Some(s.to_string()),
+            None if item.get("workspace").and_then(|v| v.as_bool()) == Some(true) => {
+                self.inherited_package_field(key)
+            }
+            None => None,
+        }
+    }
+
+    /// Read a string field from `[workspace.package]` in a manifest document
+    fn workspace_package_field(document: &Document, key: &str) -> Option<String> {
+        let value = document.get("workspace")?.get("package")?.get(key)?;
+        value.as_str().map(str::to_string)
+    }
+
+    /// Find the workspace root at or above this manifest and read a
+    /// `[workspace.package]` field from it
+    fn inherited_package_field(&self, key: &str) -> Option<String> {
+        if self.document.get("workspace").is_some() {
+            return Self::workspace_package_field(&self.document, key);
+        }
+
+        let manifest = std::fs::canonicalize(&self.path).ok()?;
+        manifest.parent()?.ancestors().skip(1).find_map(|dir| {
+            let content = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+            let document = content.parse::<Document>().ok()?;
+            document.get("workspace")?;
+            Self::workspace_package_field(&document, key)
         })
     }
 
@@ -55,53 +248,70 @@ impl CargoTomlParser {
         deps.extend(self.get_dependencies_from_section(DependencySection::Dependencies));
         deps.extend(self.get_dependencies_from_section(DependencySection::DevDependencies));
         deps.extend(self.get_dependencies_from_section(DependencySection::BuildDependencies));
+        deps.extend(self.get_dependencies_from_section(DependencySection::WorkspaceDependencies));
 
         deps
     }
 
+    /// Look up a section's table, following dotted names like "workspace.dependencies".
+    /// The section may be a `[header]` table, dotted keys or an inline table.
+    fn section_table(&self, section: &DependencySection) -> Option<&dyn toml_edit::TableLike> {
+        section
+            .as_str()
+            .split('.')
+            .try_fold(self.document.as_item(), |item, key| item.get(key))?
+            .as_table_like()
+    }
+
+    fn section_table_mut(&mut self, section: &DependencySection) -> Option<&mut dyn toml_edit::TableLike> {
+        section
+            .as_str()
+            .split('.')
+            .try_fold(self.document.as_item_mut(), |item, key| item.get_mut(key))?
+            .as_table_like_mut()
+    }
+
     /// Get dependencies from a specific section
     fn get_dependencies_from_section(&self, section: DependencySection) -> Vec<Dependency> {
-        let section_name = section.as_str();
         let mut dependencies = Vec::new();
 
         // Check if the section exists
-        if let Some(deps_table) = self.document.get(section_name).and_then(|item| item.as_table()) {
+        if let Some(deps_table) = self.section_table(&section) {
             for (name, value) in deps_table.iter() {
-                // Extract version from either string format ("1.0.0")
-                // or table format ({ version = "1.0.0", features = [...] })
-                let version = match value {
-                    toml_edit::Item::Value(toml_edit::Value::String(s)) => {
-                        s.value().to_string()
-                    }
-                    toml_edit::Item::Value(toml_edit::Value::InlineTable(table)) => {
-                        if let Some(v) = table.get("version") {
-                            if let Some(s) = v.as_str() {
-                                s.to_string()
-                            } else {
-                                continue; // Skip if version is not a string
-                            }
-                        } else {
-                            continue; // Skip if no version field
-                        }
-                    }
-                    toml_edit::Item::Table(table) => {
-                        if let Some(v) = table.get("version") {
-                            if let Some(s) = v.as_str() {
-                                s.to_string()
-                            } else {
-                                continue;
-                            }
-                        } else {
-                            continue;
-                        }
-                    }
-                    _ => continue, // Skip other types (e.g., git dependencies, path dependencies)
+                // Extract version from either string format ("1.0.0") or a
+                // table-like entry: inline ({ version = "1.0.0" }),
+                // [dependencies.name] or dotted keys (name.version = "1.0.0")
+                let version = match value.as_str() {
+                    Some(s) => s.to_string(),
+                    None => match value.get("version").and_then(|v| v.as_str()) {
+                        Some(s) => s.to_string(),
+                        None => continue, // Skip entries without a version (e.g., git, path or workspace dependencies)
+                    },
                 };
 
+                let optional = value.get("optional").and_then(|v| v.as_bool()).unwrap_or(false);
+                let package = value.get("package").and_then(|v| v.as_str()).map(str::to_string);
+                let features = value
+                    .get("features")
+                    .and_then(|v| v.as_array())
+                    .map(|features| features.iter().filter_map(|f| f.as_str()).map(str::to_string).collect())
+                    .unwrap_or_default();
+                // Cargo also accepts the older `default_features` spelling
+                let default_features = value
+                    .get("default-features")
+                    .or_else(|| value.get("default_features"))
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(true);
+
                 dependencies.push(Dependency {
                     name: name.to_string(),
                     version,
                     section: section.clone(),
+                    optional,
+                    package,
+                    features,
+                    default_features,
+                    path: value.get("path").and_then(|v| v.as_str()).map(str::to_string),
                 });
             }
         }
@@ -109,14 +319,163 @@ impl CargoTomlParser {
         dependencies
     }
 
+    /// Get git dependencies from all sections
+    pub fn get_git_dependencies(&self) -> Vec<GitDependency> {
+        let mut dependencies = Vec::new();
+
+        for section in DependencySection::ALL {
+            let Some(deps_table) = self.section_table(&section) else {
+                continue;
+            };
+
+            for (name, value) in deps_table.iter() {
+                let Some(url) = value.get("git").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                let field = |key: &str| value.get(key).and_then(|v| v.as_str()).map(str::to_string);
+
+                let reference = if let Some(tag) = field("tag") {
+                    GitReference::Tag(tag)
+                } else if let Some(rev) = field("rev") {
+                    GitReference::Rev(rev)
+                } else if let Some(branch) = field("branch") {
+                    GitReference::Branch(branch)
+                } else {
+                    GitReference::DefaultBranch
+                };
+
+                dependencies.push(GitDependency {
+                    name: name.to_string(),
+                    url: url.to_string(),
+                    reference,
+                    section: section.clone(),
+                    optional: value.get("optional").and_then(|v| v.as_bool()).unwrap_or(false),
+                });
+            }
+        }
+
+        dependencies
+    }
+
+    /// Get path dependencies from all sections
+    pub fn get_path_dependencies(&self) -> Vec<PathDependency> {
+        let mut dependencies = Vec::new();
+
+        for section in DependencySection::ALL {
+            let Some(deps_table) = self.section_table(&section) else {
+                continue;
+            };
+
+            for (name, value) in deps_table.iter() {
+                let Some(path) = value.get("path").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+
+                dependencies.push(PathDependency {
+                    name: name.to_string(),
+                    path: path.to_string(),
+                    version: value.get("version").and_then(|v| v.as_str()).map(str::to_string),
+                    section: section.clone(),
+                    optional: value.get("optional").and_then(|v| v.as_bool()).unwrap_or(false),
+                });
+            }
+        }
+
+        dependencies
+    }
+
+    /// Get `[patch.<source>]` and `[replace]` entries
+    pub fn get_patches(&self) -> Vec<Patch> {
+        let mut patches = Vec::new();
+
+        if let Some(sources) = self.document.get("patch").and_then(|item| item.as_table_like()) {
+            for (source, entries) in sources.iter() {
+                let Some(entries) = entries.as_table_like() else {
+                    continue;
+                };
+                for (name, value) in entries.iter() {
+                    patches.push(Patch {
+                        name: name.to_string(),
+                        source: source.to_string(),
+                        package: value.get("package").and_then(|v| v.as_str()).map(str::to_string),
+                        replaces: None,
+                    });
+                }
+            }
+        }
+
+        // [replace] keys are package ID specs: "name:version", optionally prefixed by "source#"
+        if let Some(entries) = self.document.get("replace").and_then(|item| item.as_table_like()) {
+            for (spec, _) in entries.iter() {
+                let spec = spec.rsplit_once('#').map_or(spec, |(_, spec)| spec);
+                let (name, version) = match spec.split_once(':') {
+                    Some((name, version)) => (name, Some(version.to_string())),
+                    None => (spec, None),
+                };
+                patches.push(Patch {
+                    name: name.to_string(),
+                    source: "replace".to_string(),
+                    package: None,
+                    replaces: version,
+                });
+            }
+        }
+
+        patches
+    }
+
+    /// Directory containing the manifest, for resolving relative paths
+    pub fn manifest_dir(&self) -> &Path {
+        Path::new(&self.path).parent().unwrap_or(Path::new(""))
+    }
+
+    /// Point a git dependency at a different tag
+    pub fn update_git_tag(&mut self, name: &str, section: &DependencySection, new_tag: &str) -> Result<()> {
+        self.apply_edit(name, section, EditField::GitTag, |_| new_tag.to_string())
+    }
+
     /// Update a dependency version while preserving operators
     pub fn update_dependency(&mut self, name: &str, section: &DependencySection, new_version: &str) -> Result<()> {
+        self.apply_edit(name, section, EditField::Version, |old| {
+            Self::preserve_version_operator(old, new_version)
+        })
+    }
+
+    /// Replace one string of a dependency entry with `new(old)` and record the edit
+    fn apply_edit(
+        &mut self,
+        name: &str,
+        section: &DependencySection,
+        field: EditField,
+        new: impl FnOnce(&str) -> String,
+    ) -> Result<()> {
+        let value = self.field_value_mut(name, section, field)?;
+        let old = value.as_str().unwrap_or_default().to_string();
+        let new = new(&old);
+        Self::replace_string(value, &new);
+
+        self.edits.push(Edit {
+            name: name.to_string(),
+            section: section.clone(),
+            field,
+            old,
+            new,
+        });
+        Ok(())
+    }
+
+    /// Find the string value an edit targets
+    fn field_value_mut(
+        &mut self,
+        name: &str,
+        section: &DependencySection,
+        field: EditField,
+    ) -> Result<&mut toml_edit::Value> {
         let section_name = section.as_str();
 
         // Get the section from the document
-        let section_table = self.document
-            .get_mut(section_name)
-            .and_then(|item| item.as_table_mut())
+        let section_table = self
+            .section_table_mut(section)
             .ok_or_else(|| {
                 CcuError::CargoTomlParse(format!("Section [{}] not found", section_name))
             })?;
@@ -128,52 +487,51 @@ impl CargoTomlParser {
                 CcuError::CargoTomlParse(format!("Dependency '{}' not found in [{}]", name, section_name))
             })?;
 
-        // Update the version based on the format
-        match dep_entry {
-            toml_edit::Item::Value(ref mut val) => {
-                match val {
-                    toml_edit::Value::String(ref mut s) => {
-                        // Simple string format: preserve operator prefix
-                        let old_version = s.value();
-                        let new_version_with_operator = Self::preserve_version_operator(old_version, new_version);
-                        *s = toml_edit::Formatted::new(new_version_with_operator);
-                    }
-                    toml_edit::Value::InlineTable(ref mut table) => {
-                        // Inline table format: update the version field
-                        if let Some(v) = table.get_mut("version") {
-                            if let Some(old_str) = v.as_str() {
-                                let new_version_with_operator = Self::preserve_version_operator(old_str, new_version);
-                                *v = toml_edit::Value::from(new_version_with_operator);
-                            }
-                        }
-                    }
-                    _ => {
-                        return Err(CcuError::CargoTomlParse(format!(
-                            "Unsupported dependency format for '{}'",
-                            name
-                        )));
-                    }
-                }
-            }
-            toml_edit::Item::Table(ref mut table) => {
-                // Table format: update the version field
-                if let Some(toml_edit::Item::Value(ref mut v)) = table.get_mut("version") {
-                    if let toml_edit::Value::String(ref mut s) = v {
-                        let old_version = s.value();
-                        let new_version_with_operator = Self::preserve_version_operator(old_version, new_version);
-                        *s = toml_edit::Formatted::new(new_version_with_operator);
-                    }
-                }
-            }
-            _ => {
-                return Err(CcuError::CargoTomlParse(format!(
-                    "Unsupported dependency format for '{}'",
-                    name
-                )));
+        match field {
+            // The version is either the entry itself ("1.0") or its `version` key,
+            // which covers inline tables, [dependencies.name] tables and dotted keys
+            EditField::Version => {
+                let version_value = if dep_entry.is_str() {
+                    dep_entry.as_value_mut()
+                } else {
+                    dep_entry.get_mut("version").and_then(|item| item.as_value_mut())
+                };
+
+                version_value.filter(|value| value.is_str()).ok_or_else(|| {
+                    CcuError::CargoTomlParse(format!("Unsupported dependency format for '{}'", name))
+                })
             }
+            // Works for both inline tables and [dependencies.name] tables
+            EditField::GitTag => dep_entry
+                .get_mut("tag")
+                .and_then(|item| item.as_value_mut())
+                .filter(|value| value.is_str())
+                .ok_or_else(|| {
+                    CcuError::CargoTomlParse(format!("Dependency '{}' has no tag to update", name))
+                }),
         }
+    }
 
-        Ok(())
+    /// Replace a string value, keeping its quote style and the whitespace and
+    /// comments around it
+    fn replace_string(value: &mut toml_edit::Value, new: &str) {
+        let literal = match value {
+            toml_edit::Value::String(s) => s
+                .as_repr()
+                .and_then(|repr| repr.as_raw().as_str())
+                .is_some_and(|raw| raw.starts_with('\'')),
+            _ => false,
+        };
+
+        let mut replacement = if literal && !new.contains('\'') {
+            format!("'{}'", new)
+                .parse::<toml_edit::Value>()
+                .unwrap_or_else(|_| toml_edit::Value::from(new))
+        } else {
+            toml_edit::Value::from(new)
+        };
+        *replacement.decor_mut() = value.decor().clone();
+        *value = replacement;
     }
 
     /// Preserve version operators (^, ~, >=, etc.) from old version
@@ -201,9 +559,76 @@ impl CargoTomlParser {
         format!("{}{}", operator, new_version)
     }
 
-    /// Save changes back to file
-    pub fn save(&self) -> Result<()> {
-        std::fs::write(&self.path, self.document.to_string())?;
+    /// Render the (possibly modified) manifest
+    pub fn contents(&self) -> String {
+        let contents = self.document.to_string();
+        if self.crlf {
+            contents.replace('\n', "\r\n")
+        } else {
+            contents
+        }
+    }
+
+    /// Save changes back to file, atomically, rebasing them first if the file
+    /// changed since it was read
+    pub fn save(&mut self) -> Result<()> {
+        self.rebase()?;
+
+        let contents = self.contents();
+        crate::fs::write_atomic(&self.path, &contents)?;
+        self.original_hash = hash_contents(&contents);
         Ok(())
     }
+
+    /// Re-read the file and return its current contents. If it changed since
+    /// it was read, the edits made so far are re-applied to the new contents
+    /// as long as every value they replace is still as it was; otherwise the
+    /// document is left alone and `CcuError::ManifestChanged` is returned.
+    pub fn rebase(&mut self) -> Result<String> {
+        let on_disk = std::fs::read_to_string(&self.path)?;
+
+        if hash_contents(&on_disk) != self.original_hash {
+            let mut fresh = Self::parse(&on_disk, self.path.clone())?;
+            for edit in &self.edits {
+                fresh.replay(edit)?;
+            }
+            *self = fresh;
+        }
+
+        Ok(on_disk)
+    }
+
+    /// Re-apply an edit made to an older copy of this manifest
+    fn replay(&mut self, edit: &Edit) -> Result<()> {
+        let changed = CcuError::ManifestChanged {
+            path: self.path.clone(),
+            detail: format!("'{}' in [{}] was edited", edit.name, edit.section.as_str()),
+        };
+
+        let value = match self.field_value_mut(&edit.name, &edit.section, edit.field) {
+            Ok(value) if value.as_str() == Some(edit.old.as_str()) => value,
+            _ => return Err(changed),
+        };
+        Self::replace_string(value, &edit.new);
+        self.edits.push(edit.clone());
+        Ok(())
+    }
+}
+
+fn hash_contents(contents: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Parse a `rust-version` value such as "1.70" or "1.70.0"
+pub fn parse_rust_version(s: &str) -> Option<Version> {
+    let s = s.trim();
+    match s.split('.').count() {
+        1 => Version::parse(&format!("{}.0.0", s)).ok(),
+        2 => Version::parse(&format!("{}.0", s)).ok(),
+        _ => Version::parse(s).ok(),
+    }
 }
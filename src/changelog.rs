@@ -0,0 +1,318 @@
+use crate::error::Result;
+use crate::registry::client::RegistryClient;
+use flate2::read::GzDecoder;
+use regex::Regex;
+use semver::Version;
+use similar::{ChangeTag, TextDiff};
+use std::io::Read;
+use std::sync::OnceLock;
+
+/// Changelog file names (lowercase), most likely first
+const CHANGELOG_NAMES: &[&str] = &[
+    "changelog.md",
+    "changelog",
+    "changelog.txt",
+    "changes.md",
+    "history.md",
+    "releases.md",
+    "release-notes.md",
+    "news.md",
+];
+
+/// A changelog file shipped in a `.crate` archive
+#[derive(Debug, Clone)]
+pub struct Changelog {
+    /// File name relative to the package root, e.g. "CHANGELOG.md"
+    pub path: String,
+    pub text: String,
+}
+
+/// The part of a changelog describing one release
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub version: Version,
+    pub text: String,
+}
+
+impl Changelog {
+    /// Find the changelog at the root of a `.crate` archive, a gzipped
+    /// tarball with every file under `<name>-<version>/`
+    pub fn from_crate(archive: &[u8]) -> Result<Option<Self>> {
+        let mut tarball = tar::Archive::new(GzDecoder::new(archive));
+        let mut found: Option<(usize, Self)> = None;
+
+        for entry in tarball.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+
+            // Only files directly under the package root
+            let Some((_, file)) = path.split_once('/') else {
+                continue;
+            };
+            let Some(rank) = CHANGELOG_NAMES.iter().position(|name| file.eq_ignore_ascii_case(name)) else {
+                continue;
+            };
+            if file.contains('/') || found.as_ref().is_some_and(|(best, _)| *best <= rank) {
+                continue;
+            }
+
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+            found = Some((
+                rank,
+                Self {
+                    path: file.to_string(),
+                    text: String::from_utf8_lossy(&bytes).into_owned(),
+                },
+            ));
+        }
+
+        Ok(found.map(|(_, changelog)| changelog))
+    }
+
+    /// Sections for releases newer than `from`, up to and including `to`, in
+    /// the order they appear
+    pub fn sections_between(&self, from: &Version, to: &Version) -> Vec<Section> {
+        let lines: Vec<&str> = self.text.lines().collect();
+        let headings = headings(&lines);
+
+        let mut sections = Vec::new();
+        for (i, heading) in headings.iter().enumerate() {
+            let Some(version) = &heading.version else {
+                continue;
+            };
+            if version <= from || version > to {
+                continue;
+            }
+
+            // A section runs until the next heading at the same or a higher level
+            let end = headings[i + 1..]
+                .iter()
+                .find(|next| next.level <= heading.level)
+                .map_or(lines.len(), |next| next.line);
+
+            sections.push(Section {
+                version: version.clone(),
+                text: lines[heading.line..end].join("\n").trim_end().to_string(),
+            });
+        }
+
+        sections
+    }
+
+    /// Lines this changelog adds to an older copy of it, for changelogs whose
+    /// headings don't name versions
+    pub fn added_since(&self, older: &Changelog) -> Option<String> {
+        let diff = TextDiff::from_lines(&older.text, &self.text);
+        let added: String = diff
+            .iter_all_changes()
+            .filter(|change| change.tag() == ChangeTag::Insert)
+            .map(|change| change.value())
+            .collect();
+
+        let added = added.trim_end();
+        (!added.trim().is_empty()).then(|| added.to_string())
+    }
+}
+
+/// What a crate's changelog says about the releases between two versions
+#[derive(Debug, Clone)]
+pub enum ReleaseNotes {
+    /// Sections headed by the releases in between
+    Sections { path: String, sections: Vec<Section> },
+    /// Lines the newer changelog adds to the older one
+    Added { path: String, text: String },
+    /// The changelog doesn't mention the releases in between
+    Nothing { path: String },
+    /// The newer release ships no changelog
+    Missing,
+}
+
+impl ReleaseNotes {
+    /// Download the `.crate` for `to` and extract its changelog entries after
+    /// `from`. The `from` release is only downloaded if the headings of the
+    /// newer changelog don't name the versions in between.
+    pub async fn fetch(client: &RegistryClient, name: &str, from: &Version, to: &Version) -> Result<Self> {
+        let Some(newer) = Changelog::from_crate(&client.download_crate(name, to).await?)? else {
+            return Ok(Self::Missing);
+        };
+
+        let sections = newer.sections_between(from, to);
+        if !sections.is_empty() {
+            return Ok(Self::Sections {
+                path: newer.path,
+                sections,
+            });
+        }
+
+        let older = match client.download_crate(name, from).await {
+            Ok(archive) => Changelog::from_crate(&archive)?,
+            Err(_) => None,
+        };
+        Ok(match older.and_then(|older| newer.added_since(&older)) {
+            Some(text) => Self::Added { path: newer.path, text },
+            None => Self::Nothing { path: newer.path },
+        })
+    }
+}
+
+/// A markdown heading, ATX (`## 1.2.0`) or setext (underlined with `=` or `-`)
+struct Heading {
+    /// Index of the heading's first line
+    line: usize,
+    level: usize,
+    version: Option<Version>,
+}
+
+fn headings(lines: &[&str]) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut in_code = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+        }
+        if in_code {
+            continue;
+        }
+
+        let hashes = line.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&hashes) && line[hashes..].starts_with([' ', '\t']) {
+            headings.push(Heading {
+                line: i,
+                level: hashes,
+                version: heading_version(&line[hashes..]),
+            });
+            continue;
+        }
+
+        // Setext headings only count when they name a version: a lone `---`
+        // under a paragraph is more often a rule than a heading
+        let underline = lines.get(i + 1).map_or("", |next| next.trim());
+        let level = match underline.chars().next() {
+            Some('=') => 1,
+            Some('-') => 2,
+            _ => continue,
+        };
+        if underline.len() >= 3 && underline.chars().all(|c| c == '=' || c == '-') && !line.trim().is_empty() {
+            if let Some(version) = heading_version(line) {
+                headings.push(Heading {
+                    line: i,
+                    level,
+                    version: Some(version),
+                });
+            }
+        }
+    }
+
+    headings
+}
+
+/// The first version number in a heading, e.g. "[1.2.0] - 2024-01-01" or "v0.3 (yanked)"
+fn heading_version(text: &str) -> Option<Version> {
+    static VERSION: OnceLock<Regex> = OnceLock::new();
+    let regex = VERSION.get_or_init(|| {
+        Regex::new(r"(?:^|[^\w.])v?(\d+)\.(\d+)(?:\.(\d+))?(-[0-9A-Za-z.-]*[0-9A-Za-z])?").unwrap()
+    });
+
+    let captures = regex.captures(text)?;
+    let patch = captures.get(3).map_or("0", |m| m.as_str());
+    let pre = captures.get(4).map_or("", |m| m.as_str());
+    Version::parse(&format!("{}.{}.{}{}", &captures[1], &captures[2], patch, pre)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = "\
+# Changelog
+
+## [Unreleased]
+
+- Work in progress
+
+## [1.3.0] - 2024-05-01
+
+### Added
+
+- `Foo::bar`
+
+## [1.2.1] - 2024-03-10
+
+- Fix a panic on empty input
+
+## 1.2.0
+
+- Initial 1.2 release
+
+```text
+## 9.9.9 inside a code block
+```
+
+Version 1.1.0
+-------------
+
+- Older notes
+";
+
+    fn changelog(text: &str) -> Changelog {
+        Changelog {
+            path: "CHANGELOG.md".to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    fn versions(sections: &[Section]) -> Vec<String> {
+        sections.iter().map(|section| section.version.to_string()).collect()
+    }
+
+    #[test]
+    fn heading_versions() {
+        let cases = [
+            (" [1.2.0] - 2024-01-01", Some("1.2.0")),
+            (" v0.3 (yanked)", Some("0.3.0")),
+            (" 2.0.0-rc.1", Some("2.0.0-rc.1")),
+            (" Version 1.10.2, requires Rust 1.70", Some("1.10.2")),
+            (" foo-1.2.3", Some("1.2.3")),
+            (" Unreleased", None),
+            (" 2024-01-01", None),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(
+                heading_version(text).map(|v| v.to_string()).as_deref(),
+                expected,
+                "heading {:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn sections_between_versions() {
+        let log = changelog(CHANGELOG);
+        let v = |s: &str| Version::parse(s).unwrap();
+
+        assert_eq!(versions(&log.sections_between(&v("1.2.0"), &v("1.3.0"))), ["1.3.0", "1.2.1"]);
+        assert_eq!(versions(&log.sections_between(&v("1.0.0"), &v("1.2.0"))), ["1.2.0", "1.1.0"]);
+        assert!(log.sections_between(&v("1.3.0"), &v("2.0.0")).is_empty());
+
+        let sections = log.sections_between(&v("1.2.1"), &v("1.3.0"));
+        assert_eq!(sections[0].text, "## [1.3.0] - 2024-05-01\n\n### Added\n\n- `Foo::bar`");
+
+        // Code blocks don't start sections
+        let sections = log.sections_between(&v("1.1.0"), &v("1.2.0"));
+        assert!(sections[0].text.contains("9.9.9 inside a code block"));
+    }
+
+    #[test]
+    fn added_lines_without_version_headings() {
+        let older = changelog("# News\n\n- old change\n");
+        let newer = changelog("# News\n\n- new change\n- another\n- old change\n");
+
+        assert_eq!(newer.added_since(&older).as_deref(), Some("- new change\n- another"));
+        assert_eq!(older.added_since(&older), None);
+    }
+}
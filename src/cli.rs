@@ -1,3 +1,7 @@
+use crate::bump::BumpKind;
+use crate::cargo::parser::DependencySection;
+use crate::config::Target;
+use crate::ui::{OutputFormat, SortKey};
 use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
@@ -15,6 +19,30 @@ pub struct Cli {
     #[arg(short, long)]
     pub upgrade: bool,
 
+    /// Show a unified diff of the upgrades without writing Cargo.toml
+    #[arg(long, conflicts_with = "upgrade")]
+    pub diff: bool,
+
+    /// Save the upgrade diff to a patch file for `git apply`, without writing Cargo.toml
+    #[arg(long, value_name = "FILE", conflicts_with = "upgrade")]
+    pub write_patch: Option<String>,
+
+    /// Commit the upgraded Cargo.toml and Cargo.lock, listing each bump (implies -u)
+    #[arg(long, conflicts_with_all = ["diff", "write_patch"])]
+    pub git_commit: bool,
+
+    /// Make one commit per upgraded dependency (implies -u)
+    #[arg(long, conflicts_with_all = ["diff", "write_patch", "git_commit"])]
+    pub git_commit_each: bool,
+
+    /// Create this branch and commit the upgrades on it (implies --git-commit)
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["diff", "write_patch"])]
+    pub git_branch: Option<String>,
+
+    /// Commit even if the work tree has uncommitted changes
+    #[arg(long)]
+    pub allow_dirty: bool,
+
     /// Interactive mode - select which packages to upgrade
     #[arg(short, long)]
     pub interactive: bool,
@@ -23,13 +51,89 @@ pub struct Cli {
     #[arg(long, default_value = "Cargo.toml")]
     pub manifest_path: String,
 
-    /// Filter packages by name (glob patterns supported)
+    /// Filter packages by name: globs, /regex/, comma-separated lists, !negation
     #[arg(value_name = "PACKAGE")]
     pub filter: Vec<String>,
 
-    /// Reject specific packages (won't update these)
+    /// Reject specific packages (won't update these; globs and /regex/ supported)
     #[arg(long, value_name = "PACKAGE")]
     pub reject: Vec<String>,
+
+    /// Only check these sections: prod, dev, build, workspace
+    #[arg(long, value_name = "SECTION", value_delimiter = ',')]
+    pub dep: Vec<DependencySection>,
+
+    /// Only show updates of these kinds: breaking, compatible-feature, compatible-fix, prerelease
+    #[arg(long, value_enum, value_name = "KIND", value_delimiter = ',')]
+    pub only: Vec<BumpKind>,
+
+    /// Exit with status 1 if any update of these kinds is found
+    #[arg(long, value_enum, value_name = "KIND", value_delimiter = ',')]
+    pub fail_on: Vec<BumpKind>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OutputFormat,
+
+    /// Print changelog entries for each suggested upgrade
+    #[arg(long)]
+    pub changelog: bool,
+
+    /// Also report outdated crates in Cargo.lock that aren't direct dependencies
+    #[arg(long)]
+    pub transitive: bool,
+
+    /// List crates locked in several incompatible versions
+    #[arg(long)]
+    pub duplicates: bool,
+
+    /// Compare the licenses of current and suggested versions
+    #[arg(long)]
+    pub licenses: bool,
+
+    /// Check dependencies against a local RustSec advisory-db clone
+    #[arg(long)]
+    pub audit: bool,
+
+    /// Path to the advisory-db clone (default: ~/.cargo/advisory-db)
+    #[arg(long, value_name = "PATH")]
+    pub advisory_db: Option<String>,
+
+    /// Sort the report (default: manifest order)
+    #[arg(long, value_enum)]
+    pub sort: Option<SortKey>,
+
+    /// Only check optional dependencies
+    #[arg(long, overrides_with = "no_optional")]
+    pub optional: bool,
+
+    /// Skip optional dependencies
+    #[arg(long, overrides_with = "optional")]
+    pub no_optional: bool,
+
+    /// Limit upgrades to a version level (default: latest)
+    #[arg(short, long, value_enum)]
+    pub target: Option<Target>,
+
+    /// Ignore versions published less than this many days ago
+    #[arg(long, value_name = "DAYS")]
+    pub cooldown: Option<u32>,
+
+    /// Include prerelease versions in suggestions
+    #[arg(long)]
+    pub pre: bool,
+
+    /// Suggest versions even if they need a newer Rust than package.rust-version
+    #[arg(long)]
+    pub ignore_rust_version: bool,
+
+    /// Suggest versions even if they drop features enabled in Cargo.toml (warns instead)
+    #[arg(long)]
+    pub ignore_features: bool,
+
+    /// Path to a config file (default: .ccurc.toml next to Cargo.toml)
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -40,4 +144,18 @@ pub enum Commands {
         #[arg(long)]
         outdated: bool,
     },
+    /// Restore Cargo.toml and Cargo.lock from before the last upgrade
+    Undo,
+    /// Print a crate's changelog entries between two versions
+    Changelog {
+        /// Dependency or crate name
+        #[arg(value_name = "CRATE")]
+        name: String,
+        /// Older version (default: the version Cargo.toml requires)
+        #[arg(long)]
+        from: Option<semver::Version>,
+        /// Newer version (default: the latest release)
+        #[arg(long)]
+        to: Option<semver::Version>,
+    },
 }
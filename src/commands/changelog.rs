@@ -0,0 +1,81 @@
+use crate::cargo::{self, CargoTomlParser, Lockfile};
+use crate::changelog::ReleaseNotes;
+use crate::config::Config;
+use crate::error::{CcuError, Result};
+use crate::registry::client::RegistryClient;
+use crate::ui::OutputFormatter;
+use semver::{Version, VersionReq};
+
+pub struct ChangelogCommand {
+    pub manifest_path: String,
+    /// Dependency name as written in Cargo.toml, or a crate name
+    pub name: String,
+    pub from: Option<Version>,
+    pub to: Option<Version>,
+    pub config_path: Option<String>,
+}
+
+impl ChangelogCommand {
+    /// Print the changelog entries between the version of a dependency in
+    /// use (or `--from`) and its latest release (or `--to`). The version in
+    /// use is the locked one, or the required version without a lockfile.
+    pub async fn run(&self) -> Result<()> {
+        let config = Config::load(self.config_path.as_deref(), &self.manifest_path)?;
+        let mut client = RegistryClient::new();
+        if let Some(url) = &config.download_url {
+            client = client.with_download_url(url);
+        }
+
+        // Renamed dependencies are downloaded by their registry name
+        let dependency = CargoTomlParser::from_path(&self.manifest_path)
+            .map(|parser| parser.get_all_dependencies())
+            .unwrap_or_default()
+            .into_iter()
+            .find(|dep| dep.name == self.name || dep.crate_name() == self.name);
+        let crate_name = dependency
+            .as_ref()
+            .map_or(self.name.as_str(), |dep| dep.crate_name())
+            .to_string();
+
+        let from = match (&self.from, &dependency) {
+            (Some(from), _) => from.clone(),
+            (None, Some(dep)) => {
+                let locked = VersionReq::parse(&dep.version).ok().and_then(|req| {
+                    let lockfile = Lockfile::from_path(Lockfile::find_for_manifest(&self.manifest_path)?).ok()?;
+                    lockfile.locked_version(&crate_name, &req)
+                });
+                locked.or_else(|| cargo::requirement_version(&dep.version)).ok_or_else(|| {
+                    CcuError::CargoTomlParse(format!("Can't read a version from '{}'; pass --from", dep.version))
+                })?
+            }
+            (None, None) => {
+                return Err(CcuError::CargoTomlParse(format!(
+                    "'{}' is not a dependency in {}; pass --from",
+                    self.name, self.manifest_path
+                )))
+            }
+        };
+
+        let to = match &self.to {
+            Some(to) => to.clone(),
+            None => client
+                .get_all_versions(&crate_name)
+                .await?
+                .into_iter()
+                .filter(|info| !info.yanked && (info.version.pre.is_empty() || !from.pre.is_empty()))
+                .map(|info| info.version)
+                .max()
+                .ok_or_else(|| CcuError::RegistryQuery(format!("'{}' has no releases", crate_name)))?,
+        };
+
+        if to <= from {
+            println!("{} {} is already the latest release", crate_name, from);
+            return Ok(());
+        }
+
+        let notes = ReleaseNotes::fetch(&client, &crate_name, &from, &to).await?;
+        OutputFormatter::print_release_notes(&crate_name, &from, &to, &notes);
+
+        Ok(())
+    }
+}
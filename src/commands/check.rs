@@ -1,9 +1,30 @@
-use crate::cargo::parser::CargoTomlParser;
-use crate::error::Result;
-use crate::registry::client::RegistryClient;
+use crate::advisory::{Advisory, AdvisoryDb};
+use crate::bump::{compatibility_key, BumpKind};
+use crate::cargo::{self, backup::Backup};
+use crate::cargo::lockfile::{LockedPackage, Lockfile};
+use crate::cargo::parser::{
+    CargoTomlParser, Dependency, DependencySection, GitDependency, GitReference, Patch, PathDependency,
+};
+use crate::changelog::ReleaseNotes;
+use crate::cli::Cli;
+use crate::config::{Config, Rule, Target};
+use crate::duplicates::{self, Duplicate};
+use crate::error::{CcuError, Result};
+use crate::filter::{Filter, Pattern};
+use crate::git::remote::{tag_version, RemoteRefs};
+use crate::git::Repo;
+use crate::license::License;
+use crate::peers::{self, Peer, PeerConstraint};
+use crate::registry::client::{RegistryClient, VersionInfo};
+use crate::ui::{diff, OutputFormat, OutputFormatter, SortKey};
+use chrono::{DateTime, Duration, Utc};
 use colored::Colorize;
-use semver::Version;
+use semver::{Version, VersionReq};
+use serde_json::json;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
 pub struct CheckCommand {
     pub manifest_path: String,
@@ -11,104 +32,1173 @@ pub struct CheckCommand {
     pub interactive: bool,
     pub filter: Vec<String>,
     pub reject: Vec<String>,
+    /// Sections to check; empty means all
+    pub sections: Vec<DependencySection>,
+    /// Update kinds to report and apply; empty means all
+    pub only: Vec<BumpKind>,
+    /// Update kinds that make the run exit with status 1
+    pub fail_on: Vec<BumpKind>,
+    pub format: OutputFormat,
+    /// Report order; `None` keeps manifest order
+    pub sort: Option<SortKey>,
+    /// Print changelog entries for each suggested upgrade
+    pub changelog: bool,
+    /// Compare the licenses of current and suggested versions
+    pub licenses: bool,
+    /// Also report outdated crates in Cargo.lock that aren't direct dependencies
+    pub transitive: bool,
+    /// List crates locked in several incompatible versions
+    pub duplicates: bool,
+    /// Check dependencies against the RustSec advisory database
+    pub audit: bool,
+    /// Path to a local advisory-db clone
+    pub advisory_db: Option<String>,
+    /// `Some(true)` checks only optional dependencies, `Some(false)` skips them
+    pub optional: Option<bool>,
+    pub target: Option<Target>,
+    /// Minimum release age in days
+    pub cooldown: Option<u32>,
+    /// Don't restrict suggestions to the manifest's rust-version
+    pub ignore_rust_version: bool,
+    /// Suggest versions that drop enabled features, with a warning
+    pub ignore_features: bool,
+    /// Suggest prerelease versions
+    pub pre: bool,
+    /// Print a diff of the upgrades instead of writing them
+    pub diff: bool,
+    /// Save the diff of the upgrades to this file
+    pub write_patch: Option<String>,
+    /// Commit the upgrades to git, all at once or one per dependency
+    pub git_commit: Option<CommitMode>,
+    /// Branch to create before committing
+    pub git_branch: Option<String>,
+    /// Commit even with uncommitted changes in the work tree
+    pub allow_dirty: bool,
+    pub config_path: Option<String>,
+}
+
+/// How `--git-commit` groups upgrades into commits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitMode {
+    All,
+    Each,
+}
+
+/// A dependency with a newer release available
+struct Update<'a> {
+    dep: &'a Dependency,
+    current: Version,
+    /// Version in Cargo.lock, if there's a lockfile
+    locked: Option<Version>,
+    /// When the version in use (the locked one, if there's a lockfile) was
+    /// published, if the index records it
+    released: Option<DateTime<Utc>>,
+    /// Version to upgrade to, if the dependency's policy allows one
+    target: Option<Version>,
+    /// Newest release regardless of rules and --target, with its publish time
+    latest: Option<(Version, Option<DateTime<Utc>>)>,
+    /// Newer release excluded by a config rule, with the rule's reason
+    held_back: Option<(Version, Option<String>)>,
+    /// Newer release skipped because it needs a newer Rust, with that Rust version
+    needs_rust: Option<(Version, Version)>,
+    /// Newer release skipped because it's younger than the cooldown
+    too_fresh: Option<(Version, DateTime<Utc>)>,
+    /// Newer release lacking features enabled in Cargo.toml, with those
+    /// features; it's the target itself with `--ignore-features`
+    missing_features: Option<(Version, Vec<String>)>,
+    /// Features the target offers that the current version doesn't
+    new_features: Vec<String>,
+    /// Default features of the current version the target no longer enables by default
+    dropped_defaults: Vec<String>,
+    /// Advisories against the current version the target fixes
+    fixes: Vec<String>,
+    /// License of the current and target version, if they differ
+    license: Option<LicenseChange>,
+    /// Why the target was lowered to keep it compatible with the other dependencies
+    peer: Option<PeerConstraint>,
+    /// Duplicate crate versions in Cargo.lock this upgrade removes, e.g. "syn 1.0.109"
+    dedupes: Vec<String>,
+}
+
+/// A license that changes between the current and target version
+struct LicenseChange {
+    from: License,
+    to: License,
+    /// Whether `allowed_licenses` permits the target's license
+    allowed: bool,
+}
+
+/// A dependency whose version has known security vulnerabilities
+struct Vulnerability<'a> {
+    dep: &'a Dependency,
+    /// Locked version, or the requirement's version without a lockfile
+    version: Version,
+    advisories: Vec<&'a Advisory>,
+    /// Smallest release without known vulnerabilities the dependency's
+    /// policy allows, ignoring `--target`
+    fix: Option<Version>,
+    /// Smallest release without known vulnerabilities, if the policy rules
+    /// out every such release
+    ruled_out: Option<Version>,
+}
+
+/// A dependency whose version in use is yanked, or whose crate has
+/// informational advisories such as "unmaintained"
+struct Health<'a> {
+    dep: &'a Dependency,
+    /// Locked version, or the requirement's version without a lockfile
+    version: Version,
+    /// The locked version is yanked; never set without a lockfile
+    yanked: bool,
+    notices: Vec<&'a Advisory>,
+}
+
+/// Problems with the versions in use, whether or not newer releases exist
+#[derive(Default)]
+struct Risks<'a> {
+    vulnerabilities: Vec<Vulnerability<'a>>,
+    health: Vec<Health<'a>>,
+}
+
+impl Risks<'_> {
+    fn is_empty(&self) -> bool {
+        self.vulnerabilities.is_empty() && self.health.is_empty()
+    }
+}
+
+impl Update<'_> {
+    /// Kind of change upgrading to the target would make
+    fn bump(&self) -> Option<BumpKind> {
+        self.target.as_ref().map(|target| BumpKind::of(&self.current, target))
+    }
+
+    /// Time between the release in use and the newest one, in years
+    fn libyears(&self) -> Option<f64> {
+        let (_, latest_released) = self.latest.as_ref()?;
+        let days = ((*latest_released)? - self.released?).num_days().max(0);
+        Some(days as f64 / 365.25)
+    }
+}
+
+/// A git dependency with a newer tag or a moved branch
+struct GitUpdate<'a> {
+    dep: &'a GitDependency,
+    kind: GitUpdateKind,
+}
+
+enum GitUpdateKind {
+    /// A newer version tag exists; `via_rev` when the dependency pins a
+    /// tagged commit by `rev` rather than by `tag`
+    NewerTag {
+        current: String,
+        latest: String,
+        via_rev: bool,
+    },
+    /// The branch head differs from the commit in Cargo.lock
+    BranchMoved { locked: String, head: String },
+}
+
+/// A path dependency compared against the local crate it points to
+struct PathReport<'a> {
+    dep: &'a PathDependency,
+    /// `package.version` of the local crate, if it could be read
+    local: Option<Version>,
+}
+
+impl PathReport<'_> {
+    /// Whether the requirement used on publish no longer matches the local crate
+    fn is_stale(&self) -> bool {
+        match (&self.dep.version, &self.local) {
+            (Some(req), Some(local)) => VersionReq::parse(req).is_ok_and(|req| !req.matches(local)),
+            _ => false,
+        }
+    }
+}
+
+/// A `[patch]` or `[replace]` entry that may no longer be needed
+struct PatchReport<'a> {
+    patch: &'a Patch,
+    /// Version the patch provides, from Cargo.lock or the `[replace]` key
+    patched: Option<Version>,
+    /// Upstream release newer than the patched version
+    upstream: Option<Version>,
+    /// The patch doesn't apply to any dependency in Cargo.lock
+    unused: bool,
+}
+
+/// A crate in Cargo.lock that no manifest depends on directly, with a newer release
+struct TransitiveReport<'a> {
+    package: &'a LockedPackage,
+    version: Version,
+    latest: Version,
+    /// Dependents whose requirement rules out the newer release, with that requirement
+    pinned_by: Vec<(&'a LockedPackage, VersionReq)>,
+    /// Direct dependencies the pins come through, or that pull the crate in if nothing pins it
+    via: Vec<&'a LockedPackage>,
+    /// Direct dependencies whose suggested upgrade allows the newer release, or drops a pinning crate
+    unblocked_by: Vec<(String, Version)>,
+}
+
+/// What a check reads besides the registry: the manifest's dependencies,
+/// the config, Cargo.lock and the advisory database
+struct Inputs {
+    config: Config,
+    filter: Filter,
+    /// `--reject` patterns and the config's `reject` list
+    reject: Vec<Pattern>,
+    dependencies: Vec<Dependency>,
+    git_dependencies: Vec<GitDependency>,
+    path_dependencies: Vec<PathDependency>,
+    /// Patches that pass the filters
+    patches: Vec<Patch>,
+    lockfile: Option<Lockfile>,
+    /// Loaded with `--audit`
+    advisory_db: Option<AdvisoryDb>,
+}
+
+impl Inputs {
+    fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+            && self.git_dependencies.is_empty()
+            && self.path_dependencies.is_empty()
+            && self.patches.is_empty()
+    }
+}
+
+/// Everything a check finds, which is printed, listed as JSON and applied
+#[derive(Default)]
+struct Report<'a> {
+    risks: Risks<'a>,
+    updates: Vec<Update<'a>>,
+    /// Selected dependencies that couldn't be compared with the registry, and why
+    unchecked: Vec<(&'a Dependency, String)>,
+    /// How many selected dependencies were compared with the registry
+    checked: usize,
+    git_updates: Vec<GitUpdate<'a>>,
+    path_reports: Vec<PathReport<'a>>,
+    patch_reports: Vec<PatchReport<'a>>,
+    transitive: Vec<TransitiveReport<'a>>,
+    duplicates: Vec<Duplicate<'a>>,
+}
+
+impl Report<'_> {
+    /// Whether there's nothing to upgrade or fix
+    fn is_up_to_date(&self) -> bool {
+        self.updates.is_empty()
+            && self.risks.is_empty()
+            && self.git_updates.is_empty()
+            && self.patch_reports.is_empty()
+            && self.transitive.is_empty()
+            && !self.path_reports.iter().any(PathReport::is_stale)
+    }
+}
+
+/// The checked dependencies as peers for `peers::resolve`
+struct PeerPlan<'v> {
+    peers: Vec<Peer<'v>>,
+    /// (index into peers, index into updates) of the dependencies with an update
+    updates: Vec<(usize, usize)>,
+}
+
+/// A single edit to Cargo.toml
+struct Change<'a> {
+    name: &'a str,
+    section: &'a DependencySection,
+    /// Version or tag before the change, without requirement operators
+    from: String,
+    to: String,
+    /// Replaces a git dependency's tag rather than a version requirement
+    tag: bool,
+}
+
+impl Change<'_> {
+    fn apply(&self, parser: &mut CargoTomlParser) -> Result<()> {
+        if self.tag {
+            parser.update_git_tag(self.name, self.section, &self.to)
+        } else {
+            parser.update_dependency(self.name, self.section, &self.to)
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("{} from {} to {}", self.name, self.from, self.to)
+    }
+}
+
+/// Constraints a suggested version has to satisfy
+#[derive(Default, Clone)]
+struct VersionPolicy<'a> {
+    level: Target,
+    max: Option<&'a VersionReq>,
+    /// Skip versions published after this time
+    published_before: Option<DateTime<Utc>>,
+    /// Skip versions that need a newer Rust than this
+    rust_version: Option<&'a Version>,
+    /// Allow any prerelease, not just newer ones of a prerelease we already use
+    pre: bool,
+    /// Skip versions that don't offer all of these features
+    features: &'a [String],
+}
+
+impl VersionPolicy<'_> {
+    /// Pick the newest version the policy allows
+    fn select<'v>(&self, versions: &'v [VersionInfo], current: &Version) -> Option<&'v VersionInfo> {
+        self.allowed(versions, current).max_by(|a, b| a.version.cmp(&b.version))
+    }
+
+    /// Non-yanked versions the policy allows. Prereleases are only allowed
+    /// with `pre`, or when the current version is a prerelease of the same
+    /// major.minor.patch.
+    fn allowed<'v, 's>(
+        &'s self,
+        versions: &'v [VersionInfo],
+        current: &'s Version,
+    ) -> impl Iterator<Item = &'v VersionInfo> + 's
+    where
+        'v: 's,
+    {
+        versions
+            .iter()
+            .filter(|info| !info.yanked)
+            .filter(|info| {
+                let version = &info.version;
+                version.pre.is_empty()
+                    || self.pre
+                    || (!current.pre.is_empty()
+                        && (version.major, version.minor, version.patch)
+                            == (current.major, current.minor, current.patch))
+            })
+            .filter(|info| {
+                let compatible = compatibility_key(&info.version) == compatibility_key(current);
+                match self.level {
+                    Target::Latest => true,
+                    Target::Minor => compatible,
+                    Target::Patch => compatible && info.version.minor == current.minor,
+                }
+            })
+            .filter(|info| self.max.is_none_or(|req| req.matches(&info.version)))
+            // Versions without a publish time predate the index recording it, so they're old enough
+            .filter(|info| match (self.published_before, info.pubtime) {
+                (Some(cutoff), Some(pubtime)) => pubtime <= cutoff,
+                _ => true,
+            })
+            .filter(|info| match (self.rust_version, info.rust_version()) {
+                (Some(msrv), Some(needed)) => needed <= *msrv,
+                _ => true,
+            })
+            .filter(|info| info.missing_features(self.features).is_empty())
+    }
+
+    /// Smallest release newer than the version in use that `is_safe`
+    /// accepts. Only `--target` gives way to a fix; rules, the MSRV, enabled
+    /// features and the cooldown still apply.
+    fn fix(
+        &self,
+        versions: &[VersionInfo],
+        current: &Version,
+        in_use: &Version,
+        is_safe: impl Fn(&Version) -> bool,
+    ) -> Option<Version> {
+        let policy = VersionPolicy {
+            level: Target::Latest,
+            ..self.clone()
+        };
+        policy
+            .allowed(versions, current)
+            .filter(|info| info.version > *in_use && is_safe(&info.version))
+            .map(|info| info.version.clone())
+            .min()
+    }
+
+    /// Newest release only the MSRV ruled out, with the Rust it needs, if
+    /// it's newer than what we suggest
+    fn needs_rust(
+        &self,
+        versions: &[VersionInfo],
+        current: &Version,
+        target: Option<&Version>,
+    ) -> Option<(Version, Version)> {
+        VersionPolicy {
+            rust_version: None,
+            ..self.clone()
+        }
+        .select(versions, current)
+        .filter(|info| info.version > *current && Some(&info.version) > target)
+        .and_then(|info| Some((info.version.clone(), info.rust_version()?)))
+    }
+
+    /// Newest release only the cooldown skipped, with its publish time, if
+    /// it's newer than what we suggest
+    fn too_fresh(
+        &self,
+        versions: &[VersionInfo],
+        current: &Version,
+        target: Option<&Version>,
+    ) -> Option<(Version, DateTime<Utc>)> {
+        VersionPolicy {
+            published_before: None,
+            ..self.clone()
+        }
+        .select(versions, current)
+        .filter(|info| info.version > *current && Some(&info.version) > target)
+        .and_then(|info| Some((info.version.clone(), info.pubtime?)))
+    }
 }
 
 impl CheckCommand {
-    pub fn new(
-        manifest_path: String,
-        upgrade: bool,
-        interactive: bool,
-        filter: Vec<String>,
-        reject: Vec<String>,
-    ) -> Self {
+    pub fn from_cli(cli: &Cli) -> Self {
         Self {
-            manifest_path,
-            upgrade,
-            interactive,
-            filter,
-            reject,
+            manifest_path: cli.manifest_path.clone(),
+            upgrade: cli.upgrade || cli.git_commit || cli.git_commit_each || cli.git_branch.is_some(),
+            interactive: cli.interactive,
+            filter: cli.filter.clone(),
+            reject: cli.reject.clone(),
+            sections: cli.dep.clone(),
+            only: cli.only.clone(),
+            fail_on: cli.fail_on.clone(),
+            format: cli.format,
+            sort: cli.sort,
+            changelog: cli.changelog,
+            licenses: cli.licenses,
+            transitive: cli.transitive,
+            duplicates: cli.duplicates,
+            audit: cli.audit,
+            advisory_db: cli.advisory_db.clone(),
+            optional: match (cli.optional, cli.no_optional) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            },
+            target: cli.target,
+            cooldown: cli.cooldown,
+            ignore_rust_version: cli.ignore_rust_version,
+            ignore_features: cli.ignore_features,
+            pre: cli.pre,
+            diff: cli.diff,
+            write_patch: cli.write_patch.clone(),
+            git_commit: if cli.git_commit_each {
+                Some(CommitMode::Each)
+            } else if cli.git_commit || cli.git_branch.is_some() {
+                Some(CommitMode::All)
+            } else {
+                None
+            },
+            git_branch: cli.git_branch.clone(),
+            allow_dirty: cli.allow_dirty,
+            config_path: cli.config.clone(),
         }
     }
 
-    pub async fn run(&self) -> Result<()> {
-        println!("Checking {}\n", self.manifest_path);
+    /// Check for updates and apply them if asked to. Fails with status 1 if
+    /// an update matched `--fail-on`.
+    pub async fn run(&self) -> Result<ExitCode> {
+        let json = self.format == OutputFormat::Json;
+        match self.format {
+            OutputFormat::Table => println!("Checking {}\n", self.manifest_path),
+            // The summary header that follows starts with a blank line
+            OutputFormat::Group => println!("Checking {}", self.manifest_path),
+            OutputFormat::Json => {}
+        }
 
-        // 1. Parse Cargo.toml
+        // 1. Parse Cargo.toml and load config
         let mut parser = CargoTomlParser::from_path(&self.manifest_path)?;
+        let inputs = self.load(&parser)?;
+        if inputs.is_empty() {
+            if json {
+                self.print_json(&Report::default());
+            } else {
+                println!("No dependencies found.");
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        // 2. Query the registry and work out what to suggest
+        let mut registry_client = RegistryClient::new();
+        if let Some(url) = &inputs.config.download_url {
+            registry_client = registry_client.with_download_url(url);
+        }
+        let mut report = self.build_report(&parser, &inputs, &registry_client).await?;
+
+        // 3. Display results
+        if let Some(sort) = self.sort {
+            Self::sort_updates(&mut report.updates, sort);
+        }
+
+        let gated = report
+            .updates
+            .iter()
+            .filter(|update| update.bump().is_some_and(|kind| self.fail_on.contains(&kind)))
+            .count();
+        let status = if gated > 0 { ExitCode::FAILURE } else { ExitCode::SUCCESS };
+
+        if json {
+            self.print_json(&report);
+            return Ok(status);
+        }
+
+        self.print_report(&report, &registry_client).await;
+        if gated > 0 {
+            println!("\n{}", format!("{} update(s) match --fail-on", gated).red().bold());
+        }
+
+        if report.is_up_to_date() {
+            if !report.path_reports.is_empty() {
+                println!();
+            }
+            println!("{}", "All dependencies are up to date!".green());
+            return Ok(status);
+        }
+
+        // 4. Preview or write the changes
+        self.apply(&mut parser, &report)?;
+        Ok(status)
+    }
+
+    /// Read what the check looks at besides the registry, warning about a
+    /// missing Cargo.lock with `--transitive` and unreadable advisories
+    fn load(&self, parser: &CargoTomlParser) -> Result<Inputs> {
+        let config = Config::load(self.config_path.as_deref(), &self.manifest_path)?;
+        let filter = Filter::parse(&self.filter)?;
+        let mut reject = Pattern::parse_list(&self.reject)?;
+        reject.extend(Pattern::parse_list(&config.reject)?);
         let dependencies = parser.get_all_dependencies();
+        let git_dependencies = parser.get_git_dependencies();
+        let path_dependencies = parser.get_path_dependencies();
+        let patches: Vec<_> = parser
+            .get_patches()
+            .into_iter()
+            .filter(|patch| Self::should_check_dependency(&patch.name, &filter, &reject))
+            .collect();
+        let lockfile = Lockfile::find_for_manifest(&self.manifest_path).and_then(|path| Lockfile::from_path(path).ok());
+        if self.transitive && lockfile.is_none() {
+            eprintln!("{}", "--transitive needs a Cargo.lock; run cargo generate-lockfile first".yellow());
+        }
+        let advisory_db = if self.audit {
+            let path = self
+                .advisory_db
+                .clone()
+                .or_else(|| config.advisory_db.clone())
+                .map(PathBuf::from)
+                .or_else(AdvisoryDb::default_path)
+                .ok_or_else(|| CcuError::Advisory("no advisory-db path; pass --advisory-db".to_string()))?;
+            let db = AdvisoryDb::open(path)?;
+            if let Some(first) = db.unparsable.first() {
+                let warning = format!(
+                    "Skipped {} advisory file(s) that couldn't be parsed, e.g. {}",
+                    db.unparsable.len(),
+                    first.display()
+                );
+                eprintln!("{}", warning.yellow());
+            }
+            Some(db)
+        } else {
+            None
+        };
 
-        if dependencies.is_empty() {
-            println!("No dependencies found.");
-            return Ok(());
+        Ok(Inputs {
+            config,
+            filter,
+            reject,
+            dependencies,
+            git_dependencies,
+            path_dependencies,
+            patches,
+            lockfile,
+            advisory_db,
+        })
+    }
+
+    /// Compare everything the inputs list against the registry, git remotes
+    /// and Cargo.lock. Later steps see what earlier ones decided: peers are
+    /// resolved before licenses are checked, and refused upgrades count as
+    /// not written for peers, transitive crates and duplicates.
+    async fn build_report<'a>(
+        &self,
+        parser: &CargoTomlParser,
+        inputs: &'a Inputs,
+        client: &RegistryClient,
+    ) -> Result<Report<'a>> {
+        let Inputs {
+            config,
+            filter,
+            reject,
+            git_dependencies,
+            path_dependencies,
+            patches,
+            lockfile,
+            ..
+        } = inputs;
+        let (all_versions, query_errors) = self.fetch_versions(inputs, client).await;
+
+        let mut report = Report::default();
+        let PeerPlan {
+            mut peers,
+            updates: peer_updates,
+        } = self.check_dependencies(parser, inputs, &all_versions, &query_errors, &mut report)?;
+
+        let selected_git: Vec<_> = git_dependencies
+            .iter()
+            .filter(|dep| self.is_selected(&dep.name, &dep.section, dep.optional, config, filter, reject))
+            .collect();
+        report.git_updates = self
+            .check_git_dependencies(&selected_git, config, lockfile.as_ref())
+            .await;
+
+        report.path_reports = path_dependencies
+            .iter()
+            .filter(|dep| self.is_selected(&dep.name, &dep.section, dep.optional, config, filter, reject))
+            .map(|dep| PathReport {
+                dep,
+                local: CargoTomlParser::from_path(parser.manifest_dir().join(&dep.path).join("Cargo.toml"))
+                    .ok()
+                    .and_then(|local| local.package_version()),
+            })
+            .collect();
+
+        report.patch_reports = patches
+            .iter()
+            .map(|patch| self.check_patch(patch, lockfile.as_ref(), all_versions.get(patch.crate_name())))
+            .filter(|report| report.unused || report.upstream.is_some())
+            .collect();
+
+        // Keep upgrades of crates that depend on each other compatible
+        let constraints = peers::resolve(&mut peers);
+        Self::apply_peer_constraints(&peers, constraints, &peer_updates, &mut report.updates, &report.risks);
+
+        if self.licenses || !config.allowed_licenses.is_empty() {
+            Self::check_licenses(client, &mut report.updates, &config.allowed_licenses).await;
+            // Lowered upgrades may have licenses of their own
+            loop {
+                let lowered =
+                    Self::hold_back_refused_peers(&mut peers, &peer_updates, &mut report.updates, &report.risks);
+                if lowered.is_empty() {
+                    break;
+                }
+                for i in lowered {
+                    let update = std::slice::from_mut(&mut report.updates[i]);
+                    Self::check_licenses(client, update, &config.allowed_licenses).await;
+                }
+            }
         }
 
-        // 2. Query crates.io for each dependency in parallel
-        let registry_client = RegistryClient::new();
-        let mut handles = Vec::new();
+        let package_name = parser.package_name();
+        report.transitive = match lockfile {
+            Some(lockfile) if self.transitive => self.check_transitive(
+                lockfile,
+                package_name.as_deref(),
+                &all_versions,
+                &report.updates,
+                filter,
+                reject,
+            ),
+            _ => Vec::new(),
+        };
 
-        for dep in &dependencies {
-            // Apply filters
-            if !self.should_check_dependency(&dep.name) {
-                continue;
+        // Note which upgrades let Cargo.lock drop a duplicated crate
+        report.duplicates = match lockfile {
+            Some(lockfile) => {
+                Self::check_duplicates(lockfile, package_name.as_deref(), &all_versions, &mut report.updates)
             }
+            None => Vec::new(),
+        };
+
+        Ok(report)
+    }
+
+    /// Query the registry for each selected dependency and crates.io patch
+    /// in parallel, returning the releases of each crate and why queries failed
+    async fn fetch_versions(
+        &self,
+        inputs: &Inputs,
+        client: &RegistryClient,
+    ) -> (HashMap<String, Vec<VersionInfo>>, HashMap<String, String>) {
+        let Inputs {
+            config,
+            filter,
+            reject,
+            dependencies,
+            patches,
+            lockfile,
+            ..
+        } = inputs;
+        let mut handles = Vec::new();
+
+        // Renamed dependencies are looked up by their registry name
+        let mut crate_names: Vec<_> = dependencies
+            .iter()
+            .filter(|dep| dep.path.is_none())
+            .filter(|dep| self.is_selected(&dep.name, &dep.section, dep.optional, config, filter, reject))
+            .map(|dep| dep.crate_name().to_string())
+            .chain(
+                patches
+                    .iter()
+                    .filter(|patch| patch.patches_crates_io())
+                    .map(|patch| patch.crate_name().to_string()),
+            )
+            .collect();
+        // Finding what pins a crate needs the index entries of its
+        // dependents too, so filters only apply to what's reported
+        if let Some(lockfile) = lockfile.as_ref().filter(|_| self.transitive) {
+            crate_names.extend(
+                lockfile
+                    .packages
+                    .iter()
+                    .filter(|package| package.is_registry())
+                    .map(|package| package.name.clone()),
+            );
+        }
+        crate_names.sort();
+        crate_names.dedup();
 
-            let client = registry_client.clone();
-            let name = dep.name.clone();
+        for name in crate_names {
+            let client = client.clone();
 
             let handle = tokio::spawn(async move {
-                match client.get_latest_version(&name).await {
-                    Ok(version) => Some((name, version)),
-                    Err(_) => None, // Skip on error (e.g., crate not found)
-                }
+                let versions = client.get_all_versions(&name).await;
+                (name, versions)
             });
 
             handles.push(handle);
         }
 
-        // Collect results
-        let mut latest_versions = HashMap::new();
+        // Collect results, keeping errors (e.g., crate not found) to report later
+        let mut all_versions = HashMap::new();
+        let mut query_errors = HashMap::new();
         for handle in handles {
-            if let Ok(Some((name, version))) = handle.await {
-                latest_versions.insert(name, version);
+            match handle.await {
+                Ok((name, Ok(versions))) => {
+                    all_versions.insert(name, versions);
+                }
+                Ok((name, Err(e))) => {
+                    query_errors.insert(name, e.to_string());
+                }
+                Err(_) => {}
             }
         }
 
-        // 3. Compare versions and prepare updates
-        let mut updates = Vec::new();
-        for dep in &dependencies {
-            if let Some(latest_version) = latest_versions.get(&dep.name) {
-                // Parse current version (strip operators and normalize)
-                let current_version_str = self.strip_version_operator(&dep.version);
-                let normalized_version = self.normalize_version(&current_version_str);
-                if let Ok(current_version) = Version::parse(&normalized_version) {
-                    // Only show if there's an update
-                    if latest_version > &current_version {
-                        updates.push((dep, current_version, latest_version.clone()));
+        (all_versions, query_errors)
+    }
+
+    /// Compare the selected registry dependencies with their releases,
+    /// recording updates, risks and what couldn't be checked in the report
+    fn check_dependencies<'a, 'v>(
+        &self,
+        parser: &CargoTomlParser,
+        inputs: &'a Inputs,
+        all_versions: &'v HashMap<String, Vec<VersionInfo>>,
+        query_errors: &HashMap<String, String>,
+        report: &mut Report<'a>,
+    ) -> Result<PeerPlan<'v>> {
+        let Inputs {
+            config,
+            filter,
+            reject,
+            dependencies,
+            lockfile,
+            advisory_db,
+            ..
+        } = inputs;
+        let rust_version = if self.ignore_rust_version {
+            None
+        } else {
+            parser.rust_version()
+        };
+        let cutoff = self
+            .cooldown
+            .or(config.cooldown)
+            .map(|days| Self::cooldown_cutoff(Utc::now(), days))
+            .transpose()?;
+
+        let mut peers = Vec::new();
+        let mut peer_updates = Vec::new();
+        for dep in dependencies {
+            if !self.is_selected(&dep.name, &dep.section, dep.optional, config, filter, reject) {
+                continue;
+            }
+            // Cargo builds the local crate, so its requirement has to match
+            // that rather than the newest release
+            if dep.path.is_some() {
+                report.unchecked.push((dep, "path dependency; compared with the local crate".to_string()));
+                continue;
+            }
+            let Some(versions) = all_versions.get(dep.crate_name()) else {
+                let reason = query_errors
+                    .get(dep.crate_name())
+                    .cloned()
+                    .unwrap_or_else(|| "registry query failed".to_string());
+                report.unchecked.push((dep, reason));
+                continue;
+            };
+
+            // Parse current version (strip operators and normalize)
+            let Some(current) = cargo::requirement_version(&dep.version) else {
+                report.unchecked.push((dep, "unsupported version requirement".to_string()));
+                continue;
+            };
+            report.checked += 1;
+
+            // Features the current version offers must survive the upgrade;
+            // others (typos, `dep/feature`) are left for cargo to complain about
+            let current_info = versions.iter().find(|info| info.version == current);
+            let features: Vec<String> = match current_info {
+                Some(info) => {
+                    let offered = info.feature_names();
+                    dep.features
+                        .iter()
+                        .filter(|feature| offered.contains(feature.as_str()))
+                        .cloned()
+                        .collect()
+                }
+                None => dep.features.clone(),
+            };
+
+            let rule = config.rule_for(&dep.name, &dep.section);
+            let level = rule
+                .and_then(|rule| rule.target)
+                .or(self.target)
+                .or(config.target)
+                .unwrap_or_default();
+            let policy = VersionPolicy {
+                level,
+                max: rule.and_then(|rule| rule.max.as_ref()),
+                published_before: cutoff,
+                rust_version: rust_version.as_ref(),
+                pre: self.pre,
+                features: if self.ignore_features { &[] } else { &features },
+            };
+
+            let newest = VersionPolicy {
+                published_before: cutoff,
+                rust_version: rust_version.as_ref(),
+                pre: self.pre,
+                ..Default::default()
+            }
+            .select(versions, &current);
+            let latest = newest.map(|info| info.version.clone());
+            let mut target = policy
+                .select(versions, &current)
+                .map(|info| info.version.clone())
+                .filter(|target| target > &current);
+
+            // The version in use is the locked one, if there's a lockfile
+            let locked = VersionReq::parse(&dep.version)
+                .ok()
+                .and_then(|req| lockfile.as_ref()?.locked_version(dep.crate_name(), &req));
+            let version = locked.clone().unwrap_or_else(|| current.clone());
+
+            // Without a lockfile Cargo resolves to a newer release than a
+            // yanked requirement floor, so only a locked version can be in use
+            let yanked = locked
+                .as_ref()
+                .is_some_and(|locked| versions.iter().any(|info| &info.version == locked && info.yanked));
+            let notices = advisory_db
+                .as_ref()
+                .map(|db| db.notices(dep.crate_name(), &version))
+                .unwrap_or_default();
+            if yanked || !notices.is_empty() {
+                report.risks.health.push(Health {
+                    dep,
+                    version: version.clone(),
+                    yanked,
+                    notices,
+                });
+            }
+
+            // Check the version in use against the advisories, and make sure
+            // the suggestion fixes them even if that means leaving --target
+            let mut fixes = Vec::new();
+            if let Some(db) = &advisory_db {
+                let advisories = db.vulnerabilities(dep.crate_name(), &version);
+
+                if !advisories.is_empty() {
+                    let is_safe = |version: &Version| db.vulnerabilities(dep.crate_name(), version).is_empty();
+                    let fix = policy.fix(versions, &current, &version, is_safe);
+                    let ruled_out = match fix {
+                        Some(_) => None,
+                        None => VersionPolicy {
+                            pre: self.pre,
+                            ..Default::default()
+                        }
+                        .fix(versions, &current, &version, is_safe),
+                    };
+
+                    if !target.as_ref().is_some_and(is_safe) && fix.as_ref().is_some_and(|fix| fix > &current) {
+                        target = fix.clone();
                     }
+                    fixes = Self::fixes(&advisories, target.as_ref());
+
+                    report.risks.vulnerabilities.push(Vulnerability {
+                        dep,
+                        version: version.clone(),
+                        advisories,
+                        fix,
+                        ruled_out,
+                    });
+                }
+            }
+
+            let needs_rust = policy.needs_rust(versions, &current, target.as_ref());
+            let too_fresh = policy.too_fresh(versions, &current, target.as_ref());
+
+            // Newest release without all enabled features: skipped, or only
+            // flagged when it's the target itself (--ignore-features)
+            let missing_features = VersionPolicy {
+                features: &[],
+                ..policy
+            }
+            .select(versions, &current)
+            .filter(|info| info.version > current && Some(&info.version) >= target.as_ref())
+            .map(|info| {
+                let missing = info.missing_features(&features);
+                (info.version.clone(), missing.into_iter().map(str::to_string).collect::<Vec<_>>())
+            })
+            .filter(|(_, missing)| !missing.is_empty());
+
+            let peer = peers.len();
+            peers.push(Peer {
+                name: dep.crate_name().to_string(),
+                installed: version.clone(),
+                planned: version.clone(),
+                versions,
+                allowed: policy.allowed(versions, &current).map(|info| info.version.clone()).collect(),
+            });
+
+            // --only restricts by the kind of update we'd actually make
+            if !self.only.is_empty()
+                && !target
+                    .as_ref()
+                    .is_some_and(|target| self.only.contains(&BumpKind::of(&current, target)))
+            {
+                continue;
+            }
+
+            let held_back = Self::held_back(rule, latest.as_ref(), &current, target.as_ref());
+
+            if target.is_some()
+                || held_back.is_some()
+                || needs_rust.is_some()
+                || too_fresh.is_some()
+                || missing_features.is_some()
+            {
+                let target_info = target
+                    .as_ref()
+                    .and_then(|target| versions.iter().find(|info| &info.version == target));
+
+                let (new_features, dropped_defaults) = Self::feature_changes(dep, current_info, target_info);
+
+                if let Some(target) = target.as_ref().filter(|target| **target > version) {
+                    peers[peer].planned = target.clone();
                 }
+                peer_updates.push((peer, report.updates.len()));
+                report.updates.push(Update {
+                    dep,
+                    current,
+                    locked,
+                    // Drift counts from the release in use, not the requirement's floor
+                    released: versions
+                        .iter()
+                        .find(|info| info.version == version)
+                        .and_then(|info| info.pubtime),
+                    target,
+                    latest: newest.map(|info| (info.version.clone(), info.pubtime)),
+                    held_back,
+                    needs_rust,
+                    too_fresh,
+                    missing_features,
+                    new_features,
+                    dropped_defaults,
+                    fixes,
+                    license: None,
+                    peer: None,
+                    dedupes: Vec::new(),
+                });
             }
         }
 
-        // 4. Display results
-        if updates.is_empty() {
-            println!("{}", "All dependencies are up to date!".green());
-            return Ok(());
+        Ok(PeerPlan {
+            peers,
+            updates: peer_updates,
+        })
+    }
+
+    /// Print every section of the report that has something in it
+    async fn print_report(&self, report: &Report<'_>, client: &RegistryClient) {
+        if !report.risks.vulnerabilities.is_empty() {
+            Self::print_section_header("Security advisories", false);
+            for vulnerability in &report.risks.vulnerabilities {
+                Self::print_vulnerability(vulnerability);
+            }
+        }
+        if !report.risks.health.is_empty() {
+            Self::print_section_header("Health", !report.risks.vulnerabilities.is_empty());
+            for health in &report.risks.health {
+                Self::print_health(health);
+            }
+        }
+        if !report.risks.is_empty() && self.format == OutputFormat::Table && !report.updates.is_empty() {
+            Self::print_section_header("Updates", true);
         }
 
-        for (dep, current, latest) in &updates {
-            self.print_update(&dep.name, &dep.version, current, latest);
+        let mut printed = !report.risks.is_empty();
+        printed |= match self.format {
+            OutputFormat::Group => {
+                self.print_grouped(&report.updates, &report.unchecked, report.checked + report.unchecked.len())
+            }
+            _ => {
+                for update in &report.updates {
+                    self.print_update(update);
+                }
+                Self::print_drift(&report.updates);
+                !report.updates.is_empty()
+            }
+        };
+        if !report.git_updates.is_empty() {
+            Self::print_section_header("Git dependencies", printed);
+            for update in &report.git_updates {
+                Self::print_git_update(update);
+            }
+            printed = true;
         }
 
-        // 5. If upgrade flag is set, update Cargo.toml
-        if self.upgrade {
-            println!("\n{}", "Upgrading dependencies...".cyan());
+        if !report.path_reports.is_empty() {
+            Self::print_section_header("Path dependencies", printed);
+            for path_report in &report.path_reports {
+                Self::print_path_report(path_report);
+            }
+            printed = true;
+        }
+
+        if !report.patch_reports.is_empty() {
+            Self::print_section_header("Patches", printed);
+            for patch_report in &report.patch_reports {
+                Self::print_patch_report(patch_report);
+            }
+            printed = true;
+        }
+
+        if !report.transitive.is_empty() {
+            Self::print_section_header("Transitive dependencies", printed);
+            for transitive in &report.transitive {
+                Self::print_transitive_report(transitive);
+            }
+            printed = true;
+        }
+
+        if self.duplicates && !report.duplicates.is_empty() {
+            Self::print_section_header("Duplicate crates", printed);
+            for duplicate in &report.duplicates {
+                Self::print_duplicate(duplicate);
+            }
+            printed = true;
+        }
+
+        if self.format == OutputFormat::Group && printed {
+            OutputFormatter::print_legend();
+        }
+
+        if self.changelog {
+            Self::print_changelogs(client, &report.updates).await;
+        }
+
+        let deduped: usize = report.duplicates.iter().map(Duplicate::removed).sum();
+        if deduped > 0 {
+            println!(
+                "\n{}",
+                format!("The suggested upgrades remove {} duplicate crate version(s) from Cargo.lock", deduped).green()
+            );
+        }
+    }
+
+    /// Apply the suggested changes: print or save them as a diff, commit them,
+    /// or write them with `-u`, and otherwise say how to
+    fn apply(&self, parser: &mut CargoTomlParser, report: &Report) -> Result<()> {
+        // Upgrades that introduce a license outside the allowlist are never written
+        let upgrades = report
+            .updates
+            .iter()
+            .filter(|update| update.license.as_ref().is_none_or(|license| license.allowed));
+        let upgrades = upgrades.filter_map(|update| {
+            update.target.as_ref().map(|target| Change {
+                name: &update.dep.name,
+                section: &update.dep.section,
+                from: cargo::strip_version_operator(&update.dep.version),
+                to: target.to_string(),
+                tag: false,
+            })
+        });
+        let retags = report.git_updates.iter().filter_map(|update| match &update.kind {
+            GitUpdateKind::NewerTag {
+                current,
+                latest,
+                via_rev: false,
+            } => Some(Change {
+                name: &update.dep.name,
+                section: &update.dep.section,
+                from: current.clone(),
+                to: latest.clone(),
+                tag: true,
+            }),
+            _ => None,
+        });
+        // Point stale path requirements at the local crate's version
+        let path_fixes = report
+            .path_reports
+            .iter()
+            .filter(|report| report.is_stale())
+            .filter_map(|report| {
+                Some(Change {
+                    name: &report.dep.name,
+                    section: &report.dep.section,
+                    from: cargo::strip_version_operator(report.dep.version.as_deref()?),
+                    to: report.local.as_ref()?.to_string(),
+                    tag: false,
+                })
+            });
+        let changes: Vec<_> = upgrades.chain(retags).chain(path_fixes).collect();
+
+        let denied: Vec<_> = report
+            .updates
+            .iter()
+            .filter(|update| update.license.as_ref().is_some_and(|license| !license.allowed))
+            .map(|update| update.dep.name.clone())
+            .collect();
+        if !denied.is_empty() {
+            println!(
+                "\n{}",
+                format!("Not upgrading {}: license not in allowed_licenses", Self::format_names(&denied)).yellow()
+            );
+        }
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        // Preview the changes, or update Cargo.toml if the upgrade flag is set
+        if self.diff || self.write_patch.is_some() {
+            for change in &changes {
+                change.apply(parser)?;
+            }
+            let original = parser.rebase()?;
+            let patch = diff::unified_diff(&original, &parser.contents(), &diff::patch_path(&self.manifest_path));
 
-            for (dep, _, latest) in &updates {
-                parser.update_dependency(&dep.name, &dep.section, &latest.to_string())?;
+            if self.diff {
+                println!();
+                diff::print_diff(&patch);
+            }
+            if let Some(path) = &self.write_patch {
+                std::fs::write(path, &patch)?;
+                println!("\nPatch written to {} (apply with git apply {})", path, path);
+            }
+        } else if let Some(mode) = self.git_commit {
+            self.upgrade_and_commit(parser, &changes, mode)?;
+        } else if self.upgrade {
+            println!("\n{}", "Upgrading dependencies...".cyan());
+            for change in &changes {
+                change.apply(parser)?;
             }
 
-            parser.save()?;
+            let backup = self.backup_and_save(parser)?;
             println!("{}", "Cargo.toml has been updated!".green());
+            println!("Backup saved to {} (restore with ccu undo)", backup.dir.display());
         } else {
             println!("\n{}", format!("Run ccu -u to upgrade {}", self.manifest_path).bold());
         }
@@ -116,85 +1206,1572 @@ impl CheckCommand {
         Ok(())
     }
 
-    /// Check if a dependency should be checked based on filter/reject patterns
-    fn should_check_dependency(&self, name: &str) -> bool {
-        // If filters are specified, only check matching dependencies
-        if !self.filter.is_empty() {
-            let matches = self.filter.iter().any(|pattern| {
-                // Simple wildcard matching (can be improved with glob crate)
-                if pattern.contains('*') {
-                    let pattern = pattern.replace('*', "");
-                    name.contains(&pattern)
-                } else {
-                    name == pattern
-                }
-            });
-            if !matches {
-                return false;
+    /// Upgrade Cargo.toml, refresh Cargo.lock and commit the result, on a new
+    /// branch if `--git-branch` was given
+    fn upgrade_and_commit(&self, parser: &mut CargoTomlParser, changes: &[Change], mode: CommitMode) -> Result<()> {
+        let repo = Repo::discover(parser.manifest_dir())?;
+        if !self.allow_dirty && repo.is_dirty()? {
+            return Err(CcuError::Git(
+                "the work tree has uncommitted changes; commit or stash them, or pass --allow-dirty".to_string(),
+            ));
+        }
+
+        // Only commit the lockfile if git tracks it
+        let lockfile = Lockfile::find_for_manifest(&self.manifest_path).filter(|path| repo.is_tracked(path));
+        let mut paths = vec![PathBuf::from(&self.manifest_path)];
+        paths.extend(lockfile.clone());
+
+        if let Some(branch) = &self.git_branch {
+            repo.create_branch(branch)?;
+            println!("\nSwitched to new branch {}", branch.bold());
+        }
+
+        println!("\n{}", "Upgrading dependencies...".cyan());
+        let batches: Vec<&[Change]> = match mode {
+            CommitMode::All => vec![changes],
+            CommitMode::Each => changes.chunks(1).collect(),
+        };
+
+        let mut backup = None;
+        for batch in batches {
+            for change in batch {
+                change.apply(parser)?;
+            }
+            match backup {
+                None => backup = Some(self.backup_and_save(parser)?),
+                Some(_) => parser.save()?,
+            }
+            if lockfile.is_some() {
+                self.update_lockfile()?;
             }
+
+            let message = Self::commit_message(batch);
+            repo.commit(&paths, &message)?;
+            println!("{} {}", "Committed".green(), message.lines().next().unwrap_or_default());
+        }
+
+        if let Some(backup) = backup {
+            println!("Backup saved to {} (restore with ccu undo)", backup.dir.display());
+        }
+        Ok(())
+    }
+
+    /// Back up the manifest and lockfile, then save the manifest. A failed
+    /// save writes nothing, so its backup is deleted rather than left for
+    /// `ccu undo` to pick up.
+    fn backup_and_save(&self, parser: &mut CargoTomlParser) -> Result<Backup> {
+        let backup = Backup::create(&self.manifest_path)?;
+        if let Err(e) = parser.save() {
+            let _ = backup.discard();
+            return Err(e);
+        }
+        Ok(backup)
+    }
+
+    /// Bring Cargo.lock in line with the edited requirements without
+    /// updating anything else
+    fn update_lockfile(&self) -> Result<()> {
+        let output = std::process::Command::new("cargo")
+            .args(["update", "--workspace", "--quiet", "--manifest-path"])
+            .arg(&self.manifest_path)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(CcuError::Lockfile(format!(
+                "cargo update failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
         }
+        Ok(())
+    }
 
-        // If rejects are specified, skip matching dependencies
-        if !self.reject.is_empty() {
-            let matches = self.reject.iter().any(|pattern| {
-                if pattern.contains('*') {
-                    let pattern = pattern.replace('*', "");
-                    name.contains(&pattern)
-                } else {
-                    name == pattern
+    /// "Bump serde from 1.0 to 1.0.200", or a summary with one line per bump
+    fn commit_message(changes: &[Change]) -> String {
+        match changes {
+            [change] => format!("Bump {}", change.describe()),
+            _ => {
+                let mut message = format!("Upgrade {} dependencies\n", changes.len());
+                for change in changes {
+                    message.push_str(&format!("\n- Bump {}", change.describe()));
                 }
-            });
-            if matches {
-                return false;
+                message
             }
         }
+    }
 
-        true
+    /// Latest publish time a release may have to be `days` old at `now`
+    fn cooldown_cutoff(now: DateTime<Utc>, days: u32) -> Result<DateTime<Utc>> {
+        Duration::try_days(i64::from(days))
+            .and_then(|cooldown| now.checked_sub_signed(cooldown))
+            .ok_or_else(|| CcuError::Config(format!("cooldown of {} days is too long", days)))
     }
 
-    /// Strip version operators from version string
-    fn strip_version_operator(&self, version: &str) -> String {
-        let version = version.trim();
-        if version.starts_with("^") || version.starts_with("~") || version.starts_with("=") {
-            version[1..].to_string()
-        } else if version.starts_with(">=") || version.starts_with("<=") {
-            version[2..].to_string()
-        } else if version.starts_with('>') || version.starts_with('<') {
-            version[1..].to_string()
-        } else {
-            version.to_string()
-        }
+    /// Newest release a rule keeps us from suggesting, with the rule's reason.
+    /// Only rules report held-back versions; a plain --target is expected to hold back.
+    fn held_back(
+        rule: Option<&Rule>,
+        latest: Option<&Version>,
+        current: &Version,
+        target: Option<&Version>,
+    ) -> Option<(Version, Option<String>)> {
+        let (rule, latest) = (rule?, latest?);
+        (latest > current && Some(latest) != target).then(|| (latest.clone(), rule.reason.clone()))
+    }
+
+    /// Check a dependency against the filters, section/optional flags and ignore rules
+    fn is_selected(
+        &self,
+        name: &str,
+        section: &DependencySection,
+        optional: bool,
+        config: &Config,
+        filter: &Filter,
+        reject: &[Pattern],
+    ) -> bool {
+        Self::should_check_dependency(name, filter, reject)
+            && (self.sections.is_empty() || self.sections.contains(section))
+            && self.optional.is_none_or(|only_optional| only_optional == optional)
+            && !config.rule_for(name, section).is_some_and(|rule| rule.ignore)
     }
 
-    /// Normalize version string to semver format (major.minor.patch)
-    /// Cargo allows shorthand like "0.21" or "2", but semver requires full format
-    fn normalize_version(&self, version: &str) -> String {
-        let parts: Vec<&str> = version.split('.').collect();
-        match parts.len() {
-            1 => format!("{}.0.0", parts[0]),  // "2" → "2.0.0"
-            2 => format!("{}.{}.0", parts[0], parts[1]),  // "0.21" → "0.21.0"
-            _ => version.to_string(),  // Already complete or invalid
+    /// Compare git dependencies against their remotes' tags and branch heads
+    async fn check_git_dependencies<'a>(
+        &self,
+        dependencies: &[&'a GitDependency],
+        config: &Config,
+        lockfile: Option<&Lockfile>,
+    ) -> Vec<GitUpdate<'a>> {
+        // Each dependency needs a `git ls-remote`; run them in parallel
+        let handles: Vec<_> = dependencies
+            .iter()
+            .map(|dep| {
+                let url = dep.url.clone();
+                tokio::task::spawn_blocking(move || RemoteRefs::fetch(&url))
+            })
+            .collect();
+
+        let mut updates = Vec::new();
+        for (dep, handle) in dependencies.iter().zip(handles) {
+            // Skip on error (e.g., unreachable remote)
+            let Ok(Ok(refs)) = handle.await else {
+                continue;
+            };
+
+            let kind = match &dep.reference {
+                GitReference::Tag(tag) => self.newer_tag(dep, &refs, tag, config).map(|latest| {
+                    GitUpdateKind::NewerTag {
+                        current: tag.clone(),
+                        latest,
+                        via_rev: false,
+                    }
+                }),
+                GitReference::Rev(rev) => refs.tag_for_commit(rev).and_then(|tag| {
+                    self.newer_tag(dep, &refs, tag, config)
+                        .map(|latest| GitUpdateKind::NewerTag {
+                            current: tag.to_string(),
+                            latest,
+                            via_rev: true,
+                        })
+                }),
+                GitReference::Branch(branch) => Self::branch_moved(
+                    refs.branches.get(branch),
+                    lockfile.and_then(|lockfile| lockfile.locked_git_commit(&dep.name, &dep.url)),
+                ),
+                GitReference::DefaultBranch => Self::branch_moved(
+                    refs.head.as_ref(),
+                    lockfile.and_then(|lockfile| lockfile.locked_git_commit(&dep.name, &dep.url)),
+                ),
+            };
+
+            if let Some(kind) = kind {
+                updates.push(GitUpdate { dep, kind });
+            }
         }
+
+        updates
     }
 
-    /// Print a single update line
-    fn print_update(&self, name: &str, current_with_op: &str, current: &Version, latest: &Version) {
-        let arrow = "→";
+    /// Find a newer version tag with the same prefix as `tag`, applying the
+    /// same target level, rules and prerelease policy as registry versions
+    fn newer_tag(&self, dep: &GitDependency, refs: &RemoteRefs, tag: &str, config: &Config) -> Option<String> {
+        let (prefix, current) = tag_version(tag)?;
+        let tags: HashMap<Version, &str> = refs
+            .versioned_tags(prefix)
+            .map(|(tag, version)| (version, tag))
+            .collect();
+        let versions: Vec<_> = tags.keys().cloned().map(VersionInfo::new).collect();
 
-        let colored_latest = if latest.major > current.major {
-            latest.to_string().red().bold()
-        } else if latest.minor > current.minor {
-            latest.to_string().cyan()
-        } else {
-            latest.to_string().green()
+        let rule = config.rule_for(&dep.name, &dep.section);
+        let policy = VersionPolicy {
+            level: rule
+                .and_then(|rule| rule.target)
+                .or(self.target)
+                .or(config.target)
+                .unwrap_or_default(),
+            max: rule.and_then(|rule| rule.max.as_ref()),
+            pre: self.pre,
+            ..Default::default()
         };
 
-        println!(
-            " {:<30} {:>10}  {}  {}",
-            name,
-            current_with_op,
-            arrow,
-            colored_latest
-        );
+        policy
+            .select(&versions, &current)
+            .filter(|info| info.version > current)
+            .map(|info| tags[&info.version].to_string())
+    }
+
+    /// Report a branch dependency whose locked commit isn't the branch head
+    fn branch_moved(head: Option<&String>, locked: Option<&str>) -> Option<GitUpdateKind> {
+        match (head, locked) {
+            (Some(head), Some(locked)) if !head.starts_with(locked) => Some(GitUpdateKind::BranchMoved {
+                locked: locked.to_string(),
+                head: head.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Compare a patch against upstream releases and the lockfile. Without a
+    /// lockfile we can't tell whether a patch is used.
+    fn check_patch<'a>(
+        &self,
+        patch: &'a Patch,
+        lockfile: Option<&Lockfile>,
+        versions: Option<&Vec<VersionInfo>>,
+    ) -> PatchReport<'a> {
+        let name = patch.crate_name();
+        let locked = lockfile.and_then(|lockfile| lockfile.patched_version(name));
+        let unused_patch = lockfile.and_then(|lockfile| lockfile.unused_patch(name));
+
+        let patched = patch
+            .replaces
+            .as_deref()
+            .or(locked)
+            .or(unused_patch.map(|package| package.version.as_str()))
+            .and_then(|version| Version::parse(&cargo::normalize_version(version)).ok());
+
+        let upstream = match (&patched, versions) {
+            (Some(patched), Some(versions)) => VersionPolicy {
+                pre: self.pre,
+                ..Default::default()
+            }
+            .select(versions, patched)
+            .map(|info| info.version.clone())
+            .filter(|upstream| upstream > patched),
+            _ => None,
+        };
+
+        let unused = lockfile.is_some() && (unused_patch.is_some() || locked.is_none());
+
+        PatchReport {
+            patch,
+            patched,
+            upstream,
+            unused,
+        }
+    }
+
+    /// Find crates in Cargo.lock that aren't direct dependencies and have newer
+    /// releases, and what holds each one back
+    fn check_transitive<'a>(
+        &self,
+        lockfile: &'a Lockfile,
+        package_name: Option<&str>,
+        all_versions: &HashMap<String, Vec<VersionInfo>>,
+        updates: &[Update],
+        filter: &Filter,
+        reject: &[Pattern],
+    ) -> Vec<TransitiveReport<'a>> {
+        let graph = lockfile.graph();
+        // What a release requires of a crate, if the index records it
+        let requirement = |package: &str, version: &Version, name: &str| {
+            let info = all_versions.get(package)?.iter().find(|info| &info.version == version)?;
+            info.deps
+                .iter()
+                .filter(|dep| dep.kind.as_deref() != Some("dev"))
+                .find(|dep| dep.package.as_deref().unwrap_or(&dep.name) == name)
+                .and_then(|dep| VersionReq::parse(&dep.req).ok())
+        };
+
+        let mut reports = Vec::new();
+        for package in &lockfile.packages {
+            if !package.is_registry()
+                || graph.is_direct(package)
+                || !Self::should_check_dependency(&package.name, filter, reject)
+            {
+                continue;
+            }
+            let (Ok(version), Some(versions)) = (Version::parse(&package.version), all_versions.get(&package.name)) else {
+                continue;
+            };
+            let Some(latest) = VersionPolicy {
+                pre: self.pre,
+                ..Default::default()
+            }
+            .select(versions, &version)
+            .map(|info| info.version.clone())
+            .filter(|latest| latest > &version) else {
+                continue;
+            };
+
+            let pinned_by = graph
+                .dependents_of(package)
+                .into_iter()
+                .filter_map(|dependent| {
+                    let dependent_version = Version::parse(&dependent.version).ok()?;
+                    let req = requirement(&dependent.name, &dependent_version, &package.name)?;
+                    (!req.matches(&latest)).then_some((dependent, req))
+                })
+                .collect::<Vec<_>>();
+
+            // Pins come through the direct dependencies that pull in each pinning crate
+            let mut via: Vec<&LockedPackage> = Vec::new();
+            let mut unblocked_by = Vec::new();
+            for (dependent, _) in &pinned_by {
+                for ancestor in graph.direct_ancestors(dependent) {
+                    if !via.iter().any(|&known| std::ptr::eq(known, ancestor)) {
+                        via.push(ancestor);
+                    }
+                    // Upgrades with a disallowed license won't be written
+                    let Some((update, target)) = updates
+                        .iter()
+                        .filter(|update| update.dep.crate_name() == ancestor.name)
+                        .filter(|update| update.license.as_ref().is_none_or(|license| license.allowed))
+                        .find_map(|update| Some((update, update.target.as_ref()?)))
+                    else {
+                        continue;
+                    };
+                    // A direct pinner's new release must allow the newer version; further
+                    // up, the upgrade must let Cargo drop the pinner altogether
+                    let unblocks = if std::ptr::eq(ancestor, *dependent) {
+                        requirement(&dependent.name, target, &package.name).is_some_and(|req| req.matches(&latest))
+                    } else {
+                        let upgrade = HashMap::from([(ancestor.name.clone(), target.clone())]);
+                        !duplicates::survives(lockfile, package_name, &upgrade, all_versions, dependent)
+                    };
+                    if unblocks && !unblocked_by.iter().any(|(name, _)| name == &update.dep.name) {
+                        unblocked_by.push((update.dep.name.clone(), target.clone()));
+                    }
+                }
+            }
+            if pinned_by.is_empty() {
+                via = graph.direct_dependents(package);
+            }
+            via.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+
+            reports.push(TransitiveReport {
+                package,
+                version,
+                latest,
+                pinned_by,
+                via,
+                unblocked_by,
+            });
+        }
+
+        reports.sort_by(|a, b| (&a.package.name, &a.version).cmp(&(&b.package.name, &b.version)));
+        reports
+    }
+
+    /// Find crates locked in several incompatible versions, and note on each
+    /// suggested upgrade the copies it lets Cargo drop
+    fn check_duplicates<'a>(
+        lockfile: &'a Lockfile,
+        package_name: Option<&str>,
+        all_versions: &HashMap<String, Vec<VersionInfo>>,
+        updates: &mut [Update],
+    ) -> Vec<Duplicate<'a>> {
+        // Upgrades with a disallowed license won't be written
+        let mut upgrades: HashMap<String, Version> = HashMap::new();
+        for update in updates.iter() {
+            let allowed = update.license.as_ref().is_none_or(|license| license.allowed);
+            if let Some(target) = update.target.as_ref().filter(|_| allowed) {
+                let planned = upgrades.entry(update.dep.crate_name().to_string()).or_insert_with(|| target.clone());
+                if target > planned {
+                    *planned = target.clone();
+                }
+            }
+        }
+
+        let duplicates = duplicates::find_duplicates(lockfile, package_name, &upgrades, all_versions);
+        for copy in duplicates.iter().flat_map(|duplicate| &duplicate.copies).filter(|copy| copy.removed) {
+            let removed = format!("{} {}", copy.package.name, copy.package.version);
+            for update in updates.iter_mut() {
+                if upgrades.contains_key(update.dep.crate_name())
+                    && copy.via.iter().any(|package| package.name == update.dep.crate_name())
+                    && !update.dedupes.contains(&removed)
+                {
+                    update.dedupes.push(removed.clone());
+                }
+            }
+        }
+
+        duplicates
+    }
+
+    /// Move the updates of the peers `peers::resolve` lowered to their new
+    /// targets, returning the indices of those updates
+    fn apply_peer_constraints(
+        peers: &[Peer],
+        constraints: Vec<Option<PeerConstraint>>,
+        peer_updates: &[(usize, usize)],
+        updates: &mut [Update],
+        risks: &Risks,
+    ) -> Vec<usize> {
+        let mut lowered = Vec::new();
+        for &(peer, i) in peer_updates {
+            let Some(constraint) = constraints[peer].clone() else {
+                continue;
+            };
+            let update = &mut updates[i];
+            let versions = peers[peer].versions;
+            let planned = &peers[peer].planned;
+
+            update.target = Some(planned.clone())
+                .filter(|target| target > &peers[peer].installed && target > &update.current);
+            let current_info = versions.iter().find(|info| info.version == update.current);
+            let target_info = update
+                .target
+                .as_ref()
+                .and_then(|target| versions.iter().find(|info| &info.version == target));
+            (update.new_features, update.dropped_defaults) = Self::feature_changes(update.dep, current_info, target_info);
+            let vulnerability = risks
+                .vulnerabilities
+                .iter()
+                .find(|vulnerability| std::ptr::eq(vulnerability.dep, update.dep));
+            if let Some(vulnerability) = vulnerability {
+                update.fixes = Self::fixes(&vulnerability.advisories, update.target.as_ref());
+            }
+            // The license was checked for the old target
+            update.license = None;
+            update.peer = Some(constraint);
+            lowered.push(i);
+        }
+        lowered
+    }
+
+    /// Upgrades with a disallowed license aren't written, so plan their peers
+    /// as if they stayed on the version in use. Returns the indices of the
+    /// updates that were lowered as a result.
+    fn hold_back_refused_peers(
+        peers: &mut [Peer],
+        peer_updates: &[(usize, usize)],
+        updates: &mut [Update],
+        risks: &Risks,
+    ) -> Vec<usize> {
+        let mut refused = false;
+        for &(peer, i) in peer_updates {
+            let allowed = updates[i].license.as_ref().is_none_or(|license| license.allowed);
+            if !allowed && peers[peer].planned > peers[peer].installed {
+                peers[peer].planned = peers[peer].installed.clone();
+                refused = true;
+            }
+        }
+        if !refused {
+            return Vec::new();
+        }
+
+        let constraints = peers::resolve(peers);
+        Self::apply_peer_constraints(peers, constraints, peer_updates, updates, risks)
+    }
+
+    /// Check if a dependency should be checked based on filter/reject patterns
+    fn should_check_dependency(name: &str, filter: &Filter, reject: &[Pattern]) -> bool {
+        // Only check dependencies the filter lets through, and skip rejected ones
+        filter.matches(name) && !reject.iter().any(|pattern| pattern.matches(name))
+    }
+
+    /// IDs of the advisories against the version in use that the target fixes
+    fn fixes(advisories: &[&Advisory], target: Option<&Version>) -> Vec<String> {
+        advisories
+            .iter()
+            .filter(|advisory| target.is_some_and(|target| !advisory.affects(target)))
+            .map(|advisory| advisory.id.clone())
+            .collect()
+    }
+
+    /// Features a target adds, and default features of the current version
+    /// the target drops if the dependency uses default features
+    fn feature_changes(
+        dep: &Dependency,
+        current_info: Option<&VersionInfo>,
+        target_info: Option<&VersionInfo>,
+    ) -> (Vec<String>, Vec<String>) {
+        let (Some(current_info), Some(target_info)) = (current_info, target_info) else {
+            return (Vec::new(), Vec::new());
+        };
+
+        let old = current_info.feature_names();
+        // Features starting with an underscore are internal by convention
+        let new_features = target_info
+            .feature_names()
+            .into_iter()
+            .filter(|name| !old.contains(name) && !name.starts_with('_'))
+            .map(str::to_string)
+            .collect();
+
+        let new_defaults = target_info.default_features();
+        let dropped_defaults = if dep.default_features {
+            current_info
+                .default_features()
+                .into_iter()
+                .filter(|name| !new_defaults.contains(name))
+                .map(str::to_string)
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        (new_features, dropped_defaults)
+    }
+
+    /// Print a single update line, with notes about newer releases we didn't pick
+    fn print_update(&self, update: &Update) {
+        let arrow = "→";
+
+        let colored_target = match (&update.target, update.bump()) {
+            (Some(target), Some(kind)) => OutputFormatter::paint(kind, &format!("{:<10}", target.to_string())),
+            _ => format!("{:<10}", "").normal(),
+        };
+
+        let date = |time: Option<DateTime<Utc>>| time.map_or_else(|| "?".to_string(), |time| time.format("%Y-%m-%d").to_string());
+        let released = match &update.latest {
+            Some((_, latest_released)) => {
+                let drift = update
+                    .libyears()
+                    .map_or_else(String::new, |years| format!(", {:.1} libyears", years));
+                format!("{} → {}{}", date(update.released), date(*latest_released), drift)
+            }
+            None => String::new(),
+        };
+
+        let line = format!(
+            " {:<30} {:>10}  {}  {}  {}",
+            update.dep.name,
+            update.dep.version,
+            if update.target.is_some() { arrow } else { " " },
+            colored_target,
+            released.dimmed()
+        );
+
+        let mut notes = Vec::new();
+        if let Some((latest, reason)) = &update.held_back {
+            notes.push(match reason {
+                Some(reason) => format!("{} held back: {}", latest, reason),
+                None => format!("{} held back", latest),
+            });
+        }
+        if let Some((version, rust_version)) = &update.needs_rust {
+            notes.push(format!(
+                "{} needs Rust {}.{}",
+                version, rust_version.major, rust_version.minor
+            ));
+        }
+        if let Some((version, pubtime)) = &update.too_fresh {
+            notes.push(format!("{} skipped: published {}", version, Self::format_age(pubtime)));
+        }
+        if let Some((version, missing)) = &update.missing_features {
+            let missing = Self::format_names(missing);
+            if update.target.as_ref() == Some(version) {
+                notes.push(format!("warning: {} has no feature {}", version, missing));
+            } else {
+                notes.push(format!("{} skipped: no feature {}", version, missing));
+            }
+        }
+        if !update.dropped_defaults.is_empty() {
+            notes.push(format!("no longer default: {}", Self::format_names(&update.dropped_defaults)));
+        }
+        if let Some(constraint) = &update.peer {
+            notes.push(constraint.to_string());
+        }
+        if !update.dedupes.is_empty() {
+            notes.push(format!("removes duplicate {}", Self::format_names(&update.dedupes)));
+        }
+        if let Some(license) = &update.license {
+            let change = match (&license.from, &license.to) {
+                (License::Unknown, License::Unknown) => "license unknown".to_string(),
+                (from, to) => format!("license {} → {}", from, to),
+            };
+            notes.push(if license.allowed {
+                change
+            } else {
+                format!("{}, not allowed", change).red().bold().to_string()
+            });
+        }
+        if !update.fixes.is_empty() {
+            notes.push(format!("fixes {}", update.fixes.join(", ")));
+        }
+        if !update.new_features.is_empty() {
+            notes.push(format!("new features: {}", Self::format_names(&update.new_features)));
+        }
+
+        if notes.is_empty() {
+            println!("{}", line);
+        } else {
+            println!("{}  {}", line, format!("({})", notes.join(", ")).yellow());
+        }
+    }
+
+    /// Print a vulnerable dependency with its advisories and the release that fixes them
+    fn print_vulnerability(vulnerability: &Vulnerability) {
+        let fix = match (&vulnerability.fix, &vulnerability.ruled_out) {
+            (Some(fix), _) => format!("→  {}", fix.to_string().green()),
+            (None, Some(ruled_out)) => format!("no allowed fixed release ({} ruled out)", ruled_out).red().to_string(),
+            (None, None) => "no fixed release".red().to_string(),
+        };
+        println!(
+            " {:<30} {:>10}  {}",
+            vulnerability.dep.name.red().bold(),
+            vulnerability.version,
+            fix
+        );
+
+        for advisory in &vulnerability.advisories {
+            println!("   {} {}", advisory.id.red(), advisory.title);
+        }
+    }
+
+    /// Print a dependency whose version in use is yanked or has informational advisories
+    fn print_health(health: &Health) {
+        let mut problems = Vec::new();
+        if health.yanked {
+            problems.push("yanked".red().bold().to_string());
+        }
+        problems.extend(
+            health
+                .notices
+                .iter()
+                .filter_map(|advisory| advisory.informational.as_deref())
+                .map(|kind| kind.yellow().bold().to_string()),
+        );
+        println!(
+            " {:<30} {:>10}  {}",
+            health.dep.name.bold(),
+            health.version,
+            problems.join(", ")
+        );
+
+        for advisory in &health.notices {
+            println!("   {} {}", advisory.id.yellow(), advisory.title);
+        }
+    }
+
+    /// Read the licenses of the current and target release of every suggested
+    /// upgrade from the registry's metadata, falling back to the `.crate`
+    /// archive, and record license changes. An empty allowlist allows every
+    /// license; one that can't be read is never allowed by a non-empty list.
+    async fn check_licenses(client: &RegistryClient, updates: &mut [Update<'_>], allowed: &[String]) {
+        let handles: Vec<_> = updates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, update)| {
+                let target = update.target.clone()?;
+                let (client, name, current) = (client.clone(), update.dep.crate_name().to_string(), update.current.clone());
+
+                Some(tokio::spawn(async move {
+                    let from = License::fetch(&client, &name, &current).await;
+                    let to = License::fetch(&client, &name, &target).await;
+                    (i, from, to)
+                }))
+            })
+            .collect();
+
+        for handle in handles {
+            let Ok((i, from, to)) = handle.await else {
+                continue;
+            };
+            let update = &mut updates[i];
+            for e in [&from, &to].into_iter().filter_map(|license| license.as_ref().err()) {
+                eprintln!("{} {}: {}", "Couldn't check the license of".yellow(), update.dep.name, e);
+            }
+
+            // A license we couldn't read may have changed, and no allowlist permits it
+            let (from, to) = (from.unwrap_or(License::Unknown), to.unwrap_or(License::Unknown));
+            if from != to || to == License::Unknown {
+                let allowed = allowed.is_empty() || to.is_allowed(allowed);
+                update.license = Some(LicenseChange { from, to, allowed });
+            }
+        }
+    }
+
+    /// Download and print the changelog entries of every suggested upgrade
+    async fn print_changelogs(client: &RegistryClient, updates: &[Update<'_>]) {
+        let handles: Vec<_> = updates
+            .iter()
+            .filter_map(|update| {
+                // Entries since the version in use, not the requirement's floor
+                let current = update.locked.clone().unwrap_or_else(|| update.current.clone());
+                let target = update.target.clone().filter(|target| target > &current)?;
+                let (client, name) = (client.clone(), update.dep.crate_name().to_string());
+
+                Some(tokio::spawn(async move {
+                    let notes = ReleaseNotes::fetch(&client, &name, &current, &target).await;
+                    (name, current, target, notes)
+                }))
+            })
+            .collect();
+
+        Self::print_section_header("Changelogs", true);
+        println!();
+        for handle in handles {
+            let Ok((name, current, target, notes)) = handle.await else {
+                continue;
+            };
+            match notes {
+                Ok(notes) => OutputFormatter::print_release_notes(&name, &current, &target, &notes),
+                Err(e) => println!("{} {} → {}: {}\n", name, current, target, e.to_string().red()),
+            }
+        }
+    }
+
+    /// Print the total libyear drift of the listed updates
+    fn print_drift(updates: &[Update]) {
+        let drifts: Vec<_> = updates.iter().filter_map(Update::libyears).collect();
+        if drifts.is_empty() {
+            return;
+        }
+
+        let unknown = updates.len() - drifts.len();
+        let mut line = format!(
+            "Total drift: {:.1} libyears across {} dependencies",
+            drifts.iter().sum::<f64>(),
+            drifts.len()
+        );
+        if unknown > 0 {
+            line.push_str(&format!(" ({} without release dates)", unknown));
+        }
+        println!("\n{}", line.bold());
+    }
+
+    /// Order updates for `--sort`, keeping manifest order among equals
+    fn sort_updates(updates: &mut [Update], sort: SortKey) {
+        match sort {
+            // Most drift first, then the oldest current release
+            SortKey::Age => updates.sort_by(|a, b| {
+                b.libyears()
+                    .partial_cmp(&a.libyears())
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| a.released.cmp(&b.released))
+            }),
+            SortKey::Name => updates.sort_by(|a, b| a.dep.name.cmp(&b.dep.name)),
+            // Breaking first; entries without a target go last
+            SortKey::Bump => updates.sort_by_key(|update| (update.bump().is_none(), update.bump())),
+            SortKey::Section => updates.sort_by_key(|update| {
+                DependencySection::ALL
+                    .iter()
+                    .position(|section| *section == update.dep.section)
+            }),
+        }
+    }
+
+    /// Print a single git dependency update line
+    fn print_git_update(update: &GitUpdate) {
+        let short = |commit: &str| commit.chars().take(7).collect::<String>();
+
+        let (current, latest) = match &update.kind {
+            GitUpdateKind::NewerTag {
+                current,
+                latest,
+                via_rev,
+            } => {
+                let current = match (&update.dep.reference, via_rev) {
+                    (GitReference::Rev(rev), true) => format!("rev {} ({})", short(rev), current),
+                    _ => format!("tag {}", current),
+                };
+                (current, latest.cyan())
+            }
+            GitUpdateKind::BranchMoved { locked, head } => {
+                let branch = match &update.dep.reference {
+                    GitReference::Branch(branch) => branch.as_str(),
+                    _ => "HEAD",
+                };
+                (format!("{} {}", branch, short(locked)), short(head).yellow())
+            }
+        };
+
+        println!(" {:<30} {:>10}  →  {}", update.dep.name, current, latest);
+    }
+
+    /// Print a path dependency with the local crate's version
+    fn print_path_report(report: &PathReport) {
+        let requirement = report.dep.version.as_deref().unwrap_or("-");
+        let local = match &report.local {
+            Some(local) => format!("local {} ({})", local, report.dep.path),
+            None => format!("local version unknown ({})", report.dep.path),
+        };
+
+        let line = format!(" {:<30} {:>10}  {}", report.dep.name, requirement, local);
+        if report.is_stale() {
+            println!("{}  {}", line, "requirement doesn't match the local crate".red());
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    /// Print a patch that's unused or has a newer upstream release
+    fn print_patch_report(report: &PatchReport) {
+        let name = format!("{} [{}]", report.patch.name, report.patch.source);
+        let patched = report
+            .patched
+            .as_ref()
+            .map_or_else(|| "-".to_string(), Version::to_string);
+
+        if report.unused {
+            println!(
+                " {:<30} {:>10}     {}",
+                name,
+                patched,
+                "patch no longer applies to any dependency".red()
+            );
+        } else if let Some(upstream) = &report.upstream {
+            println!(
+                " {:<30} {:>10}  →  {}  {}",
+                name,
+                patched,
+                upstream.to_string().cyan(),
+                "(upstream has a newer release)".yellow()
+            );
+        }
+    }
+
+    /// Print an outdated transitive crate with what pins it
+    fn print_transitive_report(report: &TransitiveReport) {
+        let mut notes = Vec::new();
+        if report.pinned_by.is_empty() {
+            notes.push(format!("cargo update -p {}@{}", report.package.name, report.version));
+        } else {
+            let pinned_by: Vec<_> = report
+                .pinned_by
+                .iter()
+                .map(|(package, req)| format!("{} {} ({})", package.name, package.version, req))
+                .collect();
+            notes.push(format!("pinned by {}", Self::format_names(&pinned_by)));
+        }
+
+        let via: Vec<_> = report.via.iter().map(|package| package.name.clone()).collect();
+        if !via.is_empty() {
+            notes.push(format!("via {}", Self::format_names(&via)));
+        }
+        for (name, target) in &report.unblocked_by {
+            notes.push(format!("upgrading {} to {} unblocks it", name, target));
+        }
+
+        println!(
+            " {:<30} {:>10}  →  {:<10}  {}",
+            report.package.name,
+            report.version,
+            report.latest.to_string().cyan(),
+            format!("({})", notes.join(", ")).yellow()
+        );
+    }
+
+    /// Print a duplicated crate with each locked version and what pulls it in
+    fn print_duplicate(duplicate: &Duplicate) {
+        println!(" {}", duplicate.name.bold());
+        for copy in &duplicate.copies {
+            let via: Vec<_> = copy.via.iter().map(|package| package.name.clone()).collect();
+            let via = if via.is_empty() {
+                String::new()
+            } else {
+                format!("via {}", Self::format_names(&via))
+            };
+            let removed = if copy.removed {
+                "(removed by the suggested upgrades)".green().to_string()
+            } else {
+                String::new()
+            };
+            println!("   {:<10}  {}  {}", copy.package.version, via, removed);
+        }
+    }
+
+    /// Print registry updates in blocks by kind of bump, after a summary of
+    /// how many dependencies were checked. Returns whether anything was printed.
+    fn print_grouped(&self, updates: &[Update], unchecked: &[(&Dependency, String)], total: usize) -> bool {
+        let outdated = updates.iter().filter(|update| update.target.is_some()).count();
+        OutputFormatter::print_header(total, outdated);
+
+        let groups = [
+            ("Patch", Some(BumpKind::CompatibleFix)),
+            ("Minor", Some(BumpKind::CompatibleFeature)),
+            ("Major (breaking)", Some(BumpKind::Breaking)),
+            ("Prerelease", Some(BumpKind::Prerelease)),
+            // Newer releases exist, but rules, MSRV or the cooldown ruled them all out
+            ("Held back", None),
+        ];
+
+        let mut printed = false;
+        for (title, kind) in groups {
+            let group: Vec<_> = updates.iter().filter(|update| update.bump() == kind).collect();
+            if group.is_empty() {
+                continue;
+            }
+
+            Self::print_section_header(&format!("{} ({})", title, group.len()), printed);
+            for update in group {
+                self.print_update(update);
+            }
+            printed = true;
+        }
+
+        Self::print_drift(updates);
+
+        if !unchecked.is_empty() {
+            Self::print_section_header(&format!("Could not check ({})", unchecked.len()), printed);
+            for (dep, reason) in unchecked {
+                println!(" {:<30} {:>10}  {}", dep.name, dep.version, reason.red());
+            }
+            printed = true;
+        }
+
+        printed
+    }
+
+    /// Print the results as one JSON document
+    fn print_json(&self, report: &Report) {
+        let Report {
+            risks,
+            updates,
+            unchecked,
+            git_updates,
+            path_reports,
+            patch_reports,
+            transitive,
+            duplicates,
+            ..
+        } = report;
+
+        let dependencies: Vec<_> = updates
+            .iter()
+            .map(|update| {
+                json!({
+                    "name": update.dep.name,
+                    "package": update.dep.crate_name(),
+                    "section": update.dep.section.as_str(),
+                    "requirement": update.dep.version,
+                    "current": update.current,
+                    "target": update.target,
+                    "released": update.released,
+                    "latest": update.latest.as_ref().map(|(version, released)| {
+                        json!({ "version": version, "released": released })
+                    }),
+                    "libyears": update.libyears(),
+                    "bump": update.bump(),
+                    "held_back": update.held_back.as_ref().map(|(version, reason)| {
+                        json!({ "version": version, "reason": reason })
+                    }),
+                    "needs_rust": update.needs_rust.as_ref().map(|(version, rust_version)| {
+                        json!({ "version": version, "rust_version": rust_version })
+                    }),
+                    "too_fresh": update.too_fresh.as_ref().map(|(version, published)| {
+                        json!({ "version": version, "published": published })
+                    }),
+                    "missing_features": update.missing_features.as_ref().map(|(version, features)| {
+                        json!({ "version": version, "features": features })
+                    }),
+                    "new_features": update.new_features,
+                    "dropped_default_features": update.dropped_defaults,
+                    "fixes": update.fixes,
+                    "dedupes": update.dedupes,
+                    "peer": update.peer.as_ref().map(|constraint| match constraint {
+                        PeerConstraint::Needs { version, peer, req } => {
+                            json!({ "kind": "needs", "version": version, "peer": peer, "requirement": req.to_string() })
+                        }
+                        PeerConstraint::RequiredBy { version, peer, req } => {
+                            json!({ "kind": "required-by", "version": version, "peer": peer, "requirement": req.to_string() })
+                        }
+                    }),
+                    "license": update.license.as_ref().map(|license| {
+                        json!({ "from": license.from.to_string(), "to": license.to.to_string(), "allowed": license.allowed })
+                    }),
+                })
+            })
+            .collect();
+
+        let advisory_json = |advisory: &&Advisory| {
+            json!({
+                "id": advisory.id,
+                "kind": advisory.informational.as_deref().unwrap_or("vulnerability"),
+                "title": advisory.title,
+                "date": advisory.date,
+                "url": advisory.url,
+                "aliases": advisory.aliases,
+            })
+        };
+
+        let vulnerabilities: Vec<_> = risks
+            .vulnerabilities
+            .iter()
+            .map(|vulnerability| {
+                let advisories: Vec<_> = vulnerability.advisories.iter().map(advisory_json).collect();
+                json!({
+                    "name": vulnerability.dep.name,
+                    "package": vulnerability.dep.crate_name(),
+                    "section": vulnerability.dep.section.as_str(),
+                    "version": vulnerability.version,
+                    "fix": vulnerability.fix,
+                    "ruled_out": vulnerability.ruled_out,
+                    "advisories": advisories,
+                })
+            })
+            .collect();
+
+        let health: Vec<_> = risks
+            .health
+            .iter()
+            .map(|health| {
+                let notices: Vec<_> = health.notices.iter().map(advisory_json).collect();
+                json!({
+                    "name": health.dep.name,
+                    "package": health.dep.crate_name(),
+                    "section": health.dep.section.as_str(),
+                    "version": health.version,
+                    "yanked": health.yanked,
+                    "notices": notices,
+                })
+            })
+            .collect();
+
+        let unchecked: Vec<_> = unchecked
+            .iter()
+            .map(|(dep, reason)| {
+                json!({
+                    "name": dep.name,
+                    "section": dep.section.as_str(),
+                    "requirement": dep.version,
+                    "reason": reason,
+                })
+            })
+            .collect();
+
+        let git: Vec<_> = git_updates
+            .iter()
+            .map(|update| {
+                let kind = match &update.kind {
+                    GitUpdateKind::NewerTag { current, latest, via_rev } => json!({
+                        "kind": "newer-tag",
+                        "current": current,
+                        "latest": latest,
+                        "via_rev": via_rev,
+                    }),
+                    GitUpdateKind::BranchMoved { locked, head } => json!({
+                        "kind": "branch-moved",
+                        "locked": locked,
+                        "head": head,
+                    }),
+                };
+                json!({
+                    "name": update.dep.name,
+                    "section": update.dep.section.as_str(),
+                    "url": update.dep.url,
+                    "update": kind,
+                })
+            })
+            .collect();
+
+        let paths: Vec<_> = path_reports
+            .iter()
+            .map(|report| {
+                json!({
+                    "name": report.dep.name,
+                    "section": report.dep.section.as_str(),
+                    "path": report.dep.path,
+                    "requirement": report.dep.version,
+                    "local": report.local,
+                    "stale": report.is_stale(),
+                })
+            })
+            .collect();
+
+        let patches: Vec<_> = patch_reports
+            .iter()
+            .map(|report| {
+                json!({
+                    "name": report.patch.name,
+                    "source": report.patch.source,
+                    "patched": report.patched,
+                    "upstream": report.upstream,
+                    "unused": report.unused,
+                })
+            })
+            .collect();
+
+        let transitive: Vec<_> = transitive
+            .iter()
+            .map(|report| {
+                let pinned_by: Vec<_> = report
+                    .pinned_by
+                    .iter()
+                    .map(|(package, req)| {
+                        json!({ "name": package.name, "version": package.version, "requirement": req.to_string() })
+                    })
+                    .collect();
+                let via: Vec<_> = report.via.iter().map(|package| &package.name).collect();
+                let unblocked_by: Vec<_> = report
+                    .unblocked_by
+                    .iter()
+                    .map(|(name, target)| json!({ "name": name, "target": target }))
+                    .collect();
+                json!({
+                    "name": report.package.name,
+                    "version": report.version,
+                    "latest": report.latest,
+                    "pinned_by": pinned_by,
+                    "via": via,
+                    "unblocked_by": unblocked_by,
+                })
+            })
+            .collect();
+
+        let duplicates_removed: usize = duplicates.iter().map(Duplicate::removed).sum();
+        let duplicates: Vec<_> = duplicates
+            .iter()
+            .map(|duplicate| {
+                let copies: Vec<_> = duplicate
+                    .copies
+                    .iter()
+                    .map(|copy| {
+                        let via: Vec<_> = copy.via.iter().map(|package| &package.name).collect();
+                        json!({ "version": copy.package.version, "via": via, "removed": copy.removed })
+                    })
+                    .collect();
+                json!({ "name": duplicate.name, "copies": copies })
+            })
+            .collect();
+
+        let report = json!({
+            "manifest": self.manifest_path,
+            "vulnerabilities": vulnerabilities,
+            "health": health,
+            "dependencies": dependencies,
+            "unchecked": unchecked,
+            "git": git,
+            "path": paths,
+            "patches": patches,
+            "transitive": transitive,
+            "duplicates": duplicates,
+            "duplicates_removed": duplicates_removed,
+        });
+        println!("{:#}", report);
+    }
+
+    /// Print a bold section title, separated from earlier output by a blank line
+    fn print_section_header(title: &str, after_output: bool) {
+        if after_output {
+            println!();
+        }
+        println!("{}", title.bold());
+    }
+
+    /// Join names for a note, eliding all but the first few
+    fn format_names(names: &[String]) -> String {
+        const SHOWN: usize = 5;
+        if names.len() <= SHOWN {
+            names.join(", ")
+        } else {
+            format!("{} and {} more", names[..SHOWN].join(", "), names.len() - SHOWN)
+        }
+    }
+
+    /// Describe how long ago a version was published, e.g. "3 days ago"
+    fn format_age(time: &DateTime<Utc>) -> String {
+        let age = Utc::now() - *time;
+        match age.num_days() {
+            0 => format!("{} hours ago", age.num_hours()),
+            1 => "1 day ago".to_string(),
+            days => format!("{} days ago", days),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn time(date: &str) -> DateTime<Utc> {
+        format!("{}T00:00:00Z", date).parse().unwrap()
+    }
+
+    /// 1.0.0 to 3.0.0, with 1.2 and 1.3 needing Rust 1.80, 1.3.0 published
+    /// recently, 2.0.0 dropping the "json" feature and 3.0.0 yanked
+    fn releases() -> Vec<VersionInfo> {
+        let list = ["1.0.0", "1.0.3", "1.1.0", "1.2.0", "1.3.0", "2.0.0", "2.1.0-beta.1", "3.0.0"];
+        list.iter()
+            .map(|version| {
+                let mut info = VersionInfo::new(Version::parse(version).unwrap());
+                info.pubtime = Some(time(if *version == "1.3.0" { "2026-10-10" } else { "2024-01-01" }));
+                if version.starts_with("1.2") || version.starts_with("1.3") {
+                    info.rust_version = Some("1.80".to_string());
+                }
+                if !version.starts_with('2') {
+                    info.features.insert("json".to_string(), Vec::new());
+                }
+                info.yanked = *version == "3.0.0";
+                info
+            })
+            .collect()
+    }
+
+    #[test]
+    fn select_matrix() {
+        let versions = releases();
+        let max = VersionReq::parse("<1.2").unwrap();
+        let (msrv_old, msrv_new) = (Version::new(1, 75, 0), Version::new(1, 85, 0));
+        let json = ["json".to_string()];
+        let minor = VersionPolicy {
+            level: Target::Minor,
+            ..Default::default()
+        };
+
+        let cases = [
+            // (policy, current, expected)
+            (VersionPolicy::default(), "1.0.0", Some("2.0.0")),
+            (VersionPolicy::default(), "3.0.0", Some("2.0.0")),
+            (minor.clone(), "1.0.0", Some("1.3.0")),
+            (VersionPolicy { level: Target::Patch, ..Default::default() }, "1.0.0", Some("1.0.3")),
+            (VersionPolicy { max: Some(&max), ..Default::default() }, "1.0.0", Some("1.1.0")),
+            (VersionPolicy { published_before: Some(time("2026-10-01")), ..minor.clone() }, "1.0.0", Some("1.2.0")),
+            (VersionPolicy { rust_version: Some(&msrv_old), ..minor.clone() }, "1.0.0", Some("1.1.0")),
+            (VersionPolicy { rust_version: Some(&msrv_new), ..minor.clone() }, "1.0.0", Some("1.3.0")),
+            (VersionPolicy { features: &json, ..Default::default() }, "1.0.0", Some("1.3.0")),
+            (VersionPolicy { pre: true, ..Default::default() }, "1.0.0", Some("2.1.0-beta.1")),
+            // Newer prereleases of a prerelease in use are fine without `pre`
+            (VersionPolicy::default(), "2.1.0-alpha.1", Some("2.1.0-beta.1")),
+            (VersionPolicy::default(), "2.0.1-alpha.1", Some("2.0.0")),
+            (VersionPolicy { level: Target::Patch, ..Default::default() }, "0.9.0", None),
+        ];
+
+        for (policy, current, expected) in cases {
+            let selected = policy.select(&versions, &Version::parse(current).unwrap());
+            let selected = selected.map(|info| info.version.to_string());
+            assert_eq!(selected.as_deref(), expected, "current {}", current);
+        }
+    }
+
+    #[test]
+    fn fixes_leave_only_the_target_level() {
+        let versions = releases();
+        let current = Version::new(1, 0, 0);
+        let max = VersionReq::parse("<1.1").unwrap();
+        let msrv = Version::new(1, 75, 0);
+        let json = ["json".to_string()];
+        let patch = VersionPolicy {
+            level: Target::Patch,
+            ..Default::default()
+        };
+        // Everything before 1.2.0 is vulnerable
+        let is_safe = |version: &Version| version >= &Version::new(1, 2, 0);
+
+        let cases = [
+            // (policy, expected)
+            (patch.clone(), Some("1.2.0")),
+            (VersionPolicy { rust_version: Some(&msrv), ..patch.clone() }, Some("2.0.0")),
+            (VersionPolicy { rust_version: Some(&msrv), features: &json, ..patch.clone() }, None),
+            (VersionPolicy { published_before: Some(time("2026-10-01")), ..patch.clone() }, Some("1.2.0")),
+            (VersionPolicy { max: Some(&max), ..patch.clone() }, None),
+        ];
+
+        for (policy, expected) in cases {
+            let fix = policy.fix(&versions, &current, &current, is_safe);
+            assert_eq!(fix.map(|fix| fix.to_string()).as_deref(), expected);
+        }
+    }
+
+    #[test]
+    fn target_levels_follow_cargo_compatibility() {
+        let versions = versions(&["0.0.3", "0.0.4", "0.3.0", "0.3.5", "0.4.0", "1.0.0", "1.0.2", "1.2.0", "2.0.0"]);
+        let level = |level| VersionPolicy {
+            level,
+            ..Default::default()
+        };
+
+        let cases = [
+            // (level, current, expected)
+            (Target::Minor, "0.0.3", Some("0.0.3")),
+            (Target::Patch, "0.0.3", Some("0.0.3")),
+            (Target::Latest, "0.0.3", Some("2.0.0")),
+            (Target::Minor, "0.3.0", Some("0.3.5")),
+            (Target::Patch, "0.3.0", Some("0.3.5")),
+            (Target::Minor, "0.4.0", Some("0.4.0")),
+            (Target::Minor, "1.0.0", Some("1.2.0")),
+            (Target::Patch, "1.0.0", Some("1.0.2")),
+        ];
+
+        for (target, current, expected) in cases {
+            let selected = level(target).select(&versions, &Version::parse(current).unwrap());
+            let selected = selected.map(|info| info.version.to_string());
+            assert_eq!(selected.as_deref(), expected, "current {}", current);
+        }
+    }
+
+    #[test]
+    fn notes_on_skipped_releases() {
+        let versions = releases();
+        let current = Version::new(1, 0, 0);
+        let (msrv_old, msrv_new) = (Version::new(1, 75, 0), Version::new(1, 85, 0));
+        let policy = |rust_version, published_before| VersionPolicy {
+            level: Target::Minor,
+            rust_version,
+            published_before,
+            ..Default::default()
+        };
+        let target = |policy: &VersionPolicy| policy.select(&versions, &current).map(|info| info.version.clone());
+
+        let msrv = policy(Some(&msrv_old), None);
+        assert_eq!(
+            msrv.needs_rust(&versions, &current, target(&msrv).as_ref()),
+            Some((Version::new(1, 3, 0), Version::new(1, 80, 0)))
+        );
+        let msrv = policy(Some(&msrv_new), None);
+        assert_eq!(msrv.needs_rust(&versions, &current, target(&msrv).as_ref()), None);
+
+        let cooldown = policy(None, Some(time("2026-10-01")));
+        assert_eq!(
+            cooldown.too_fresh(&versions, &current, target(&cooldown).as_ref()),
+            Some((Version::new(1, 3, 0), time("2026-10-10")))
+        );
+        let cooldown = policy(None, Some(time("2026-10-20")));
+        assert_eq!(cooldown.too_fresh(&versions, &current, target(&cooldown).as_ref()), None);
+
+        let rule = Rule {
+            reason: Some("waiting on 2.x migration".to_string()),
+            ..Default::default()
+        };
+        let (latest, target) = (Version::new(2, 0, 0), Version::new(1, 1, 0));
+        let cases = [
+            // (rule, target, expected)
+            (Some(&rule), Some(&target), Some((latest.clone(), rule.reason.clone()))),
+            (Some(&rule), None, Some((latest.clone(), rule.reason.clone()))),
+            (Some(&rule), Some(&latest), None),
+            (None, Some(&target), None),
+        ];
+        for (rule, target, expected) in cases {
+            assert_eq!(CheckCommand::held_back(rule, Some(&latest), &current, target), expected);
+        }
+    }
+
+    #[test]
+    fn stale_path_requirements() {
+        let cases = [
+            // (version requirement, local package.version, expected)
+            (Some("0.3"), Some("0.3.5"), false),
+            (Some("0.3"), Some("0.4.0"), true),
+            (Some("=0.3.1"), Some("0.3.2"), true),
+            (Some("^1.2"), Some("1.1.0"), true),
+            (None, Some("0.4.0"), false),
+            (Some("0.3"), None, false),
+            (Some("not a requirement"), Some("1.0.0"), false),
+        ];
+
+        for (version, local, expected) in cases {
+            let dep = PathDependency {
+                name: "helper".to_string(),
+                path: "../helper".to_string(),
+                version: version.map(str::to_string),
+                section: DependencySection::Dependencies,
+                optional: false,
+            };
+            let report = PathReport {
+                dep: &dep,
+                local: local.map(|local| Version::parse(local).unwrap()),
+            };
+            assert_eq!(report.is_stale(), expected, "{:?} against local {:?}", version, local);
+        }
+    }
+
+    fn fixture(file: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(file)
+    }
+
+    fn versions(list: &[&str]) -> Vec<VersionInfo> {
+        list.iter().map(|version| VersionInfo::new(Version::parse(version).unwrap())).collect()
+    }
+
+    #[test]
+    fn patch_reports() {
+        let command = CheckCommand::from_cli(&Cli::parse_from(["ccu"]));
+        let parser = CargoTomlParser::from_path(fixture("patches/Cargo.toml")).unwrap();
+        let patches = parser.get_patches();
+        let lockfile = Lockfile::from_path(fixture("patches/Cargo.lock")).unwrap();
+        let patch = |name: &str| patches.iter().find(|patch| patch.name == name).unwrap();
+
+        let cases: [(_, &[_], _, _, _); 5] = [
+            // (patch, upstream releases, patched, newer upstream, unused)
+            ("serde", &["1.0.150", "1.0.200"], Some("1.0.150"), Some("1.0.200"), false),
+            ("serde", &["1.0.100", "1.0.150"], Some("1.0.150"), None, false),
+            ("log", &["0.4.14", "0.4.22"], Some("0.4.14"), Some("0.4.22"), false),
+            // Cargo resolved tokio from the registry and recorded the patch as unused
+            ("tokio", &["1.28.0", "1.38.0"], Some("1.28.0"), Some("1.38.0"), true),
+            // Nothing depends on regex at all
+            ("regex", &["1.10.0"], None, None, true),
+        ];
+
+        for (name, releases, patched, newer, unused) in cases {
+            let report = command.check_patch(patch(name), Some(&lockfile), Some(&versions(releases)));
+            assert_eq!(report.patched.map(|version| version.to_string()).as_deref(), patched, "{}", name);
+            assert_eq!(report.upstream.map(|version| version.to_string()).as_deref(), newer, "{}", name);
+            assert_eq!(report.unused, unused, "{}", name);
+        }
+
+        // Without a lockfile, only [replace] keys say what's patched, and nothing is unused
+        let report = command.check_patch(patch("serde"), None, Some(&versions(&["1.0.200"])));
+        assert!(report.patched.is_none() && report.upstream.is_none() && !report.unused);
+        let report = command.check_patch(patch("log"), None, Some(&versions(&["0.4.22"])));
+        assert_eq!(report.upstream, Some(Version::new(0, 4, 22)));
+    }
+
+    #[test]
+    fn refused_saves_leave_no_backup() {
+        let dir = std::env::temp_dir().join(format!("ccu-refused-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Cargo.toml");
+        let manifest = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1.0\"\n";
+        std::fs::write(&path, manifest).unwrap();
+
+        let command = CheckCommand::from_cli(&Cli::parse_from(["ccu", "--manifest-path", path.to_str().unwrap()]));
+        let mut parser = CargoTomlParser::from_path(&path).unwrap();
+        parser.update_dependency("serde", &DependencySection::Dependencies, "1.0.200").unwrap();
+        std::fs::write(&path, manifest.replace("\"1.0\"", "\"1.0.100\"")).unwrap();
+
+        assert!(matches!(command.backup_and_save(&mut parser), Err(CcuError::ManifestChanged { .. })));
+        assert!(Backup::latest(&path).unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn dependency(name: &str, version: &str) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            version: version.to_string(),
+            section: DependencySection::Dependencies,
+            optional: false,
+            package: None,
+            features: Vec::new(),
+            default_features: true,
+            path: None,
+        }
+    }
+
+    fn update<'a>(dep: &'a Dependency, current: &str, target: &str) -> Update<'a> {
+        Update {
+            dep,
+            current: Version::parse(current).unwrap(),
+            locked: None,
+            released: None,
+            target: Some(Version::parse(target).unwrap()),
+            latest: None,
+            held_back: None,
+            needs_rust: None,
+            too_fresh: None,
+            missing_features: None,
+            new_features: Vec::new(),
+            dropped_defaults: Vec::new(),
+            fixes: Vec::new(),
+            license: None,
+            peer: None,
+            dedupes: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn unreadable_licenses_are_not_allowed() {
+        // Nothing listens on the discard port, so every download fails
+        let client = RegistryClient::with_index_url("http://127.0.0.1:9").with_download_url("http://127.0.0.1:9");
+        let dep = dependency("demo", "1.0");
+
+        for (allowed, expected) in [(vec!["MIT".to_string()], false), (Vec::new(), true)] {
+            let mut updates = [update(&dep, "1.0.0", "2.0.0")];
+            CheckCommand::check_licenses(&client, &mut updates, &allowed).await;
+
+            let license = updates[0].license.as_ref().unwrap();
+            assert_eq!((&license.from, &license.to), (&License::Unknown, &License::Unknown));
+            assert_eq!(license.allowed, expected, "allowlist {:?}", allowed);
+        }
+    }
+
+    #[test]
+    fn refused_peers_hold_back_their_dependents() {
+        let release = |version: &str, deps| VersionInfo::with_deps(Version::parse(version).unwrap(), deps);
+        let axum_versions = [
+            release("0.6.0", &[("hyper", "^0.14")]),
+            release("0.6.5", &[("hyper", "^0.14")]),
+            release("0.7.0", &[("hyper", "^1")]),
+        ];
+        let hyper_versions = [release("0.14.0", &[]), release("1.0.0", &[])];
+        let peer = |name: &str, versions, installed: &str, planned: &str| Peer {
+            name: name.to_string(),
+            installed: Version::parse(installed).unwrap(),
+            planned: Version::parse(planned).unwrap(),
+            versions,
+            allowed: versions.iter().map(|info: &VersionInfo| info.version.clone()).collect(),
+        };
+        let mut peers = [
+            peer("axum", &axum_versions[..], "0.6.0", "0.7.0"),
+            peer("hyper", &hyper_versions[..], "0.14.0", "1.0.0"),
+        ];
+        let (axum, hyper) = (dependency("axum", "0.6"), dependency("hyper", "0.14"));
+        let mut updates = [update(&axum, "0.6.0", "0.7.0"), update(&hyper, "0.14.0", "1.0.0")];
+        let peer_updates = [(0, 0), (1, 1)];
+        let risks = Risks::default();
+
+        assert!(CheckCommand::hold_back_refused_peers(&mut peers, &peer_updates, &mut updates, &risks).is_empty());
+
+        updates[1].license = Some(LicenseChange {
+            from: License::spdx("MIT"),
+            to: License::spdx("BUSL-1.1"),
+            allowed: false,
+        });
+        let lowered = CheckCommand::hold_back_refused_peers(&mut peers, &peer_updates, &mut updates, &risks);
+        assert_eq!(lowered, [0]);
+        assert_eq!(updates[0].target, Some(Version::new(0, 6, 5)));
+        assert_eq!(updates[0].peer.as_ref().unwrap().to_string(), "0.7.0 needs hyper ^1");
+        // The refused upgrade stays in the report, and isn't refused twice
+        assert_eq!(updates[1].target, Some(Version::new(1, 0, 0)));
+        assert!(CheckCommand::hold_back_refused_peers(&mut peers, &peer_updates, &mut updates, &risks).is_empty());
+    }
+
+    #[test]
+    fn transitive_pins_and_what_unblocks_them() {
+        let command = CheckCommand::from_cli(&Cli::parse_from(["ccu", "--transitive", "syn"]));
+        let lockfile = Lockfile::from_path(fixture("lockfile/Cargo.lock")).unwrap();
+        let release = |version: &str, deps| VersionInfo::with_deps(Version::parse(version).unwrap(), deps);
+        let index = HashMap::from([
+            ("syn".to_string(), vec![release("1.0.109", &[]), release("2.0.60", &[])]),
+            ("darling_core".to_string(), vec![release("0.13.4", &[("syn", "^1")])]),
+            ("darling".to_string(), vec![release("0.13.4", &[("darling_core", "=0.13.4")])]),
+            (
+                "serde_with".to_string(),
+                vec![release("1.14.0", &[("darling", "^0.13")]), release("3.0.0", &[("darling", "^0.20")])],
+            ),
+        ]);
+        let filter = Filter::parse(&command.filter).unwrap();
+        let serde_with = dependency("serde_with", "1.14");
+        let mut updates = [update(&serde_with, "1.14.0", "3.0.0")];
+
+        // Only syn is reported, but its dependents' index entries say what pins it
+        let reports = command.check_transitive(&lockfile, Some("app"), &index, &updates, &filter, &[]);
+        let [report] = &reports[..] else {
+            panic!("expected one report, got {}", reports.len());
+        };
+        assert_eq!((report.version.to_string(), report.latest.to_string()), ("1.0.109".into(), "2.0.60".into()));
+        let pinned_by: Vec<_> = report.pinned_by.iter().map(|(package, req)| format!("{} {}", package.name, req)).collect();
+        assert_eq!(pinned_by, ["darling_core ^1"]);
+        assert_eq!(report.via.iter().map(|package| package.name.as_str()).collect::<Vec<_>>(), ["serde_with"]);
+        assert_eq!(report.unblocked_by, [("serde_with".to_string(), Version::new(3, 0, 0))]);
+
+        // Refused upgrades unblock nothing
+        updates[0].license = Some(LicenseChange {
+            from: License::spdx("MIT"),
+            to: License::spdx("BUSL-1.1"),
+            allowed: false,
+        });
+        let reports = command.check_transitive(&lockfile, Some("app"), &index, &updates, &filter, &[]);
+        assert!(reports[0].unblocked_by.is_empty());
+    }
+
+    #[test]
+    fn cooldown_cutoff_rejects_overflowing_durations() {
+        let now = time("2026-10-19");
+        assert_eq!(CheckCommand::cooldown_cutoff(now, 0).unwrap(), now);
+        assert_eq!(CheckCommand::cooldown_cutoff(now, 18).unwrap(), time("2026-10-01"));
+        assert!(matches!(CheckCommand::cooldown_cutoff(now, u32::MAX), Err(CcuError::Config(_))));
     }
 }
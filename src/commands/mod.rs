@@ -1,3 +1,7 @@
+pub mod changelog;
 pub mod check;
+pub mod undo;
 
+pub use changelog::ChangelogCommand;
 pub use check::CheckCommand;
+pub use undo::UndoCommand;
@@ -0,0 +1,28 @@
+use crate::cargo::backup::Backup;
+use crate::error::{CcuError, Result};
+use colored::Colorize;
+
+pub struct UndoCommand {
+    pub manifest_path: String,
+}
+
+impl UndoCommand {
+    pub fn new(manifest_path: String) -> Self {
+        Self { manifest_path }
+    }
+
+    /// Restore the files backed up by the last upgrade run
+    pub fn run(&self) -> Result<()> {
+        let backup = Backup::latest(&self.manifest_path)?.ok_or_else(|| {
+            CcuError::Backup(format!("No backups found for {}", self.manifest_path))
+        })?;
+
+        println!("Restoring backup from {}\n", backup.dir.display());
+        for path in backup.restore()? {
+            println!(" {}", path.display());
+        }
+        println!("\n{}", "Files restored!".green());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use crate::cargo::parser::DependencySection;
+use crate::error::{CcuError, Result};
+use crate::filter::Pattern;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Config file looked up next to the manifest when `--config` isn't given
+pub const CONFIG_FILE_NAME: &str = ".ccurc.toml";
+
+/// How far an upgrade is allowed to move from the current version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    /// Newest release, including major bumps
+    #[default]
+    Latest,
+    /// Newest semver-compatible release: same major version, or same minor
+    /// for 0.x and same patch for 0.0.x
+    Minor,
+    /// Newest compatible release with the same minor version
+    Patch,
+}
+
+/// Settings read from `.ccurc.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Default target level, overridden by `--target`
+    pub target: Option<Target>,
+    /// Minimum release age in days, overridden by `--cooldown`
+    pub cooldown: Option<u32>,
+    /// Packages to reject in addition to `--reject`
+    pub reject: Vec<String>,
+    /// Per-dependency policy, first matching rule wins
+    pub rules: Vec<Rule>,
+    /// Base URL to download `.crate` files from, for registry mirrors
+    pub download_url: Option<String>,
+    /// Path to a local RustSec advisory-db clone for `--audit`
+    pub advisory_db: Option<String>,
+    /// SPDX expressions upgrades may introduce; other licenses aren't written
+    pub allowed_licenses: Vec<String>,
+}
+
+/// A policy applied to the dependencies it matches
+///
+/// ```toml
+/// [[rules]]
+/// name = "openssl"
+/// max = "<0.11"
+/// reason = "0.11 drops the vendored feature"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rule {
+    /// Package name pattern to match (exact name, glob or `/regex/`)
+    pub name: Option<Pattern>,
+    /// Dependency section to match, e.g. "dev" or "build-dependencies"
+    pub section: Option<DependencySection>,
+    /// Target level for matching dependencies
+    pub target: Option<Target>,
+    /// Version requirement every suggested version must satisfy
+    pub max: Option<semver::VersionReq>,
+    /// Explanation shown next to held-back dependencies
+    pub reason: Option<String>,
+    /// Never check or upgrade matching dependencies
+    pub ignore: bool,
+}
+
+impl Config {
+    /// Load the config from an explicit path, or from `.ccurc.toml` next to
+    /// the manifest if it exists
+    pub fn load(explicit: Option<&str>, manifest_path: &str) -> Result<Self> {
+        let path = match explicit {
+            Some(path) => Path::new(path).to_path_buf(),
+            None => {
+                let dir = Path::new(manifest_path).parent().unwrap_or(Path::new(""));
+                let path = dir.join(CONFIG_FILE_NAME);
+                if !path.exists() {
+                    return Ok(Self::default());
+                }
+                path
+            }
+        };
+
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            CcuError::Config(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+
+        toml::from_str(&content)
+            .map_err(|e| CcuError::Config(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    /// Find the first rule matching a dependency
+    pub fn rule_for(&self, name: &str, section: &DependencySection) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(name, section))
+    }
+}
+
+impl Rule {
+    /// Check whether the rule applies to a dependency. A rule with neither
+    /// `name` nor `section` matches everything.
+    pub fn matches(&self, name: &str, section: &DependencySection) -> bool {
+        let name_matches = self.name.as_ref().is_none_or(|pattern| pattern.matches(name));
+        let section_matches = self.section.as_ref().is_none_or(|s| s == section);
+
+        name_matches && section_matches
+    }
+}
@@ -0,0 +1,379 @@
+use crate::bump::compatibility_key;
+use crate::cargo::lockfile::{LockGraph, LockedPackage, Lockfile};
+use crate::registry::client::VersionInfo;
+use semver::{Version, VersionReq};
+use std::collections::{HashMap, HashSet};
+
+/// A crate locked in several semver-incompatible versions
+#[derive(Debug)]
+pub struct Duplicate<'a> {
+    pub name: &'a str,
+    /// Oldest first
+    pub copies: Vec<DuplicateCopy<'a>>,
+}
+
+/// One of the locked versions of a duplicated crate
+#[derive(Debug)]
+pub struct DuplicateCopy<'a> {
+    pub package: &'a LockedPackage,
+    /// Direct dependencies that pull this copy in, including the copy itself
+    pub via: Vec<&'a LockedPackage>,
+    /// Nothing depends on this copy once the planned upgrades are made
+    pub removed: bool,
+}
+
+impl Duplicate<'_> {
+    /// How many copies the planned upgrades remove
+    pub fn removed(&self) -> usize {
+        self.copies.iter().filter(|copy| copy.removed).count()
+    }
+}
+
+/// Find duplicated crates in a lockfile, and which copies would go away if
+/// the manifest's direct dependencies were upgraded as planned.
+///
+/// `root` is the package the manifest describes, whose dependencies the
+/// `upgrades` (crate name → target) apply to; without one, every workspace
+/// member counts. `index` holds the registry releases of the upgraded crates,
+/// for what their targets depend on.
+pub fn find_duplicates<'a>(
+    lockfile: &'a Lockfile,
+    root: Option<&str>,
+    upgrades: &HashMap<String, Version>,
+    index: &HashMap<String, Vec<VersionInfo>>,
+) -> Vec<Duplicate<'a>> {
+    let mut by_name: HashMap<&str, Vec<&LockedPackage>> = HashMap::new();
+    for package in lockfile.packages.iter().filter(|package| package.is_registry()) {
+        by_name.entry(package.name.as_str()).or_default().push(package);
+    }
+
+    let mut survival = Survival {
+        graph: lockfile.graph(),
+        root,
+        upgrades,
+        index,
+        memo: HashMap::new(),
+        visiting: HashSet::new(),
+        cycles_cut: 0,
+    };
+
+    let mut duplicates = Vec::new();
+    for (name, mut packages) in by_name {
+        let mut versions: Vec<_> = packages
+            .iter()
+            .filter_map(|package| Version::parse(&package.version).ok())
+            .collect();
+        versions.sort();
+        versions.dedup_by_key(|version| compatibility_key(version));
+        if versions.len() < 2 {
+            continue;
+        }
+
+        packages.sort_by_key(|package| Version::parse(&package.version).ok());
+        let newest = packages.len() - 1;
+        let copies = packages
+            .iter()
+            .enumerate()
+            .map(|(i, package)| {
+                let via = survival.graph.direct_ancestors(package);
+                // Everything else converges on the newest copy
+                let removed = i != newest && !survival.survives_as_copy(package);
+                DuplicateCopy { package, via, removed }
+            })
+            .collect();
+
+        duplicates.push(Duplicate { name, copies });
+    }
+
+    duplicates.sort_by(|a, b| a.name.cmp(b.name));
+    duplicates
+}
+
+/// Whether anything still depends on a locked package once the manifest's
+/// direct dependencies are upgraded as planned, with `root`, `upgrades` and
+/// `index` as for [`find_duplicates`]
+pub fn survives<'a>(
+    lockfile: &'a Lockfile,
+    root: Option<&str>,
+    upgrades: &HashMap<String, Version>,
+    index: &HashMap<String, Vec<VersionInfo>>,
+    package: &'a LockedPackage,
+) -> bool {
+    Survival {
+        graph: lockfile.graph(),
+        root,
+        upgrades,
+        index,
+        memo: HashMap::new(),
+        visiting: HashSet::new(),
+        cycles_cut: 0,
+    }
+    .survives(package)
+}
+
+/// Whether locked packages are still needed after the planned upgrades
+struct Survival<'a, 'r> {
+    graph: LockGraph<'a>,
+    root: Option<&'r str>,
+    upgrades: &'r HashMap<String, Version>,
+    index: &'r HashMap<String, Vec<VersionInfo>>,
+    /// Final answers only
+    memo: HashMap<(&'a str, &'a str), bool>,
+    /// Packages whose answer is being worked out further up the walk
+    visiting: HashSet<(&'a str, &'a str)>,
+    /// How often the walk came back to a package it was visiting
+    cycles_cut: usize,
+}
+
+impl<'a> Survival<'a, '_> {
+    fn is_root(&self, package: &LockedPackage) -> bool {
+        package.is_local() && self.root.is_none_or(|root| package.name == root)
+    }
+
+    /// Target of the manifest's upgrade of this package, if the manifest
+    /// depends on this copy of it
+    fn upgrade_of(&self, package: &LockedPackage) -> Option<&Version> {
+        let direct = self
+            .graph
+            .dependents_of(package)
+            .iter()
+            .any(|dependent| self.is_root(dependent));
+        direct.then(|| self.upgrades.get(&package.name)).flatten()
+    }
+
+    /// Whether a copy of a crate, or a compatible version of it, stays locked
+    fn survives_as_copy(&mut self, package: &'a LockedPackage) -> bool {
+        let upgraded_compatibly = match (self.upgrade_of(package), Version::parse(&package.version)) {
+            (Some(target), Ok(version)) => compatibility_key(target) == compatibility_key(&version),
+            _ => false,
+        };
+        upgraded_compatibly || self.survives(package)
+    }
+
+    /// Whether anything still depends on this exact locked package
+    fn survives(&mut self, package: &'a LockedPackage) -> bool {
+        let key = (package.name.as_str(), package.version.as_str());
+        if let Some(&survives) = self.memo.get(&key) {
+            return survives;
+        }
+        // Cycles don't keep a package alive on their own
+        if !self.visiting.insert(key) {
+            self.cycles_cut += 1;
+            return false;
+        }
+        let cycles_cut = self.cycles_cut;
+
+        let survives = self.graph.dependents_of(package).into_iter().any(|dependent| {
+            if dependent.is_local() {
+                // The manifest's own requirement goes away if it's upgraded
+                return !self.is_root(dependent) || self.upgrade_of(package).is_none();
+            }
+            if let Some(target) = self.upgrade_of(dependent) {
+                if self.requires_compatible(dependent, target, package) {
+                    return true;
+                }
+            }
+            self.survives(dependent)
+        });
+
+        self.visiting.remove(&key);
+        // A "no" reached by cutting a cycle may be a "yes" when the walk starts elsewhere
+        if survives || self.cycles_cut == cycles_cut {
+            self.memo.insert(key, survives);
+        }
+        survives
+    }
+
+    /// Whether a release of `dependent` requires a version of `package`
+    /// compatible with the locked one
+    fn requires_compatible(&self, dependent: &LockedPackage, release: &Version, package: &LockedPackage) -> bool {
+        let Ok(locked) = Version::parse(&package.version) else {
+            return true;
+        };
+        let Some(info) = self
+            .index
+            .get(&dependent.name)
+            .and_then(|versions| versions.iter().find(|info| &info.version == release))
+        else {
+            // Without index data, assume nothing changes
+            return true;
+        };
+
+        let releases = self.index.get(&package.name).map(Vec::as_slice).unwrap_or_default();
+        info.deps
+            .iter()
+            .filter(|dep| dep.kind.as_deref() != Some("dev"))
+            .filter(|dep| dep.package.as_deref().unwrap_or(&dep.name) == package.name)
+            .filter_map(|dep| VersionReq::parse(&dep.req).ok())
+            .any(|req| allows_compatible(&req, &locked, releases))
+    }
+}
+
+/// Whether a requirement allows some version compatible with `locked`, trying
+/// the known releases and the versions at each end of the requirement's bounds
+fn allows_compatible(req: &VersionReq, locked: &Version, releases: &[VersionInfo]) -> bool {
+    let key = compatibility_key(locked);
+    let lowest = Version::new(key.0, key.1, key.2);
+    let bounds = req.comparators.iter().flat_map(|comparator| {
+        let version = Version::new(
+            comparator.major,
+            comparator.minor.unwrap_or(0),
+            comparator.patch.unwrap_or(0),
+        );
+        let next = Version::new(version.major, version.minor, version.patch + 1);
+        [version, next]
+    });
+
+    [locked.clone(), lowest]
+        .into_iter()
+        .chain(releases.iter().map(|info| info.version.clone()))
+        .chain(bounds)
+        .filter(|version| compatibility_key(version) == key)
+        .any(|version| req.matches(&version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCKFILE: &str = r#"
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["old-macros", "serde", "syn 2.0.60"]
+
+[[package]]
+name = "old-macros"
+version = "0.3.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["syn 1.0.109"]
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["syn 2.0.60"]
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "syn"
+version = "2.0.60"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn index(releases: &[(&str, &str, &str)]) -> HashMap<String, Vec<VersionInfo>> {
+        let mut index: HashMap<String, Vec<VersionInfo>> = HashMap::new();
+        for (name, version, syn) in releases {
+            let info = VersionInfo::with_deps(Version::parse(version).unwrap(), &[("syn", syn)]);
+            index.entry(name.to_string()).or_default().push(info);
+        }
+        index
+    }
+
+    fn removed(duplicates: &[Duplicate]) -> Vec<String> {
+        duplicates
+            .iter()
+            .flat_map(|duplicate| &duplicate.copies)
+            .filter(|copy| copy.removed)
+            .map(|copy| format!("{} {}", copy.package.name, copy.package.version))
+            .collect()
+    }
+
+    #[test]
+    fn finds_copies_and_who_pulls_them_in() {
+        let lockfile: Lockfile = toml::from_str(LOCKFILE).unwrap();
+        let duplicates = find_duplicates(&lockfile, Some("app"), &HashMap::new(), &HashMap::new());
+
+        assert_eq!(duplicates.len(), 1);
+        let via: Vec<Vec<&str>> = duplicates[0]
+            .copies
+            .iter()
+            .map(|copy| copy.via.iter().map(|package| package.name.as_str()).collect())
+            .collect();
+        assert_eq!(via, [vec!["old-macros"], vec!["syn", "serde"]]);
+        assert!(removed(&duplicates).is_empty());
+    }
+
+    #[test]
+    fn upgrades_that_drop_old_copies() {
+        let lockfile: Lockfile = toml::from_str(LOCKFILE).unwrap();
+        let index = index(&[("old-macros", "0.3.0", "^1.0"), ("old-macros", "0.4.0", "^2.0.5")]);
+        let upgrade = |target: &str| HashMap::from([("old-macros".to_string(), Version::parse(target).unwrap())]);
+
+        let duplicates = find_duplicates(&lockfile, Some("app"), &upgrade("0.4.0"), &index);
+        assert_eq!(removed(&duplicates), ["syn 1.0.109"]);
+        assert_eq!(duplicates[0].removed(), 1);
+
+        // A release that still needs syn 1 keeps it
+        let index = self::index(&[("old-macros", "0.3.5", "^1.0.100")]);
+        let duplicates = find_duplicates(&lockfile, Some("app"), &upgrade("0.3.5"), &index);
+        assert!(removed(&duplicates).is_empty());
+
+        // Upgrades of other workspace members' dependencies don't count
+        let index = self::index(&[("old-macros", "0.4.0", "^2.0.5")]);
+        let duplicates = find_duplicates(&lockfile, Some("other"), &upgrade("0.4.0"), &index);
+        assert!(removed(&duplicates).is_empty());
+    }
+
+    #[test]
+    fn requirements_with_several_bounds() {
+        let lockfile: Lockfile = toml::from_str(LOCKFILE).unwrap();
+        let upgrade = HashMap::from([("old-macros".to_string(), Version::parse("0.3.5").unwrap())]);
+
+        // The first bound alone says nothing about syn 1
+        for req in ["<2, >=1.0.110", ">1.0.109, <1.1", "=1.0.200"] {
+            let index = index(&[("old-macros", "0.3.5", req)]);
+            let duplicates = find_duplicates(&lockfile, Some("app"), &upgrade, &index);
+            assert!(removed(&duplicates).is_empty(), "{}", req);
+        }
+        for req in [">=2.0.5, <3", "<1"] {
+            let index = index(&[("old-macros", "0.3.5", req)]);
+            let duplicates = find_duplicates(&lockfile, Some("app"), &upgrade, &index);
+            assert_eq!(removed(&duplicates), ["syn 1.0.109"], "{}", req);
+        }
+    }
+
+    #[test]
+    fn cycles_only_cut_the_walk_they_are_found_in() {
+        // macros and support depend on each other, and app only on support
+        let lockfile: Lockfile = toml::from_str(
+            r#"
+[[package]]
+name = "macros"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["support"]
+
+[[package]]
+name = "support"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["macros"]
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["support"]
+"#,
+        )
+        .unwrap();
+        let (upgrades, index) = (HashMap::new(), HashMap::new());
+        let mut survival = Survival {
+            graph: lockfile.graph(),
+            root: Some("app"),
+            upgrades: &upgrades,
+            index: &index,
+            memo: HashMap::new(),
+            visiting: HashSet::new(),
+            cycles_cut: 0,
+        };
+
+        // Walking from support reaches macros through the cycle first
+        assert!(survival.survives(&lockfile.packages[1]));
+        assert!(survival.survives(&lockfile.packages[0]));
+    }
+}
@@ -5,6 +5,27 @@ pub enum CcuError {
     #[error("Failed to parse Cargo.toml: {0}")]
     CargoTomlParse(String),
 
+    #[error("Failed to parse Cargo.lock: {0}")]
+    Lockfile(String),
+
+    #[error("{path} changed on disk while ccu was running ({detail}); nothing was written, re-run ccu")]
+    ManifestChanged { path: String, detail: String },
+
+    #[error("Git error: {0}")]
+    Git(String),
+
+    #[error("Backup error: {0}")]
+    Backup(String),
+
+    #[error("Failed to read advisory database: {0}")]
+    Advisory(String),
+
+    #[error("Invalid config: {0}")]
+    Config(String),
+
+    #[error("Invalid pattern: {0}")]
+    Pattern(String),
+
     #[error("Failed to query crates.io: {0}")]
     RegistryQuery(String),
 
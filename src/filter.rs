@@ -0,0 +1,254 @@
+use crate::error::{CcuError, Result};
+use regex::Regex;
+use std::str::FromStr;
+
+/// A package name pattern: an exact name, a glob (`serde*`, `tokio-?`,
+/// `[ab]*`) or a regex wrapped in slashes (`/^serde/`)
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Check whether a package name matches. Globs and exact names must match
+    /// the whole name; regexes match anywhere unless anchored.
+    pub fn matches(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+
+    /// Parse a list of arguments, each of which may hold comma-separated patterns
+    pub fn parse_list<S: AsRef<str>>(args: &[S]) -> Result<Vec<Self>> {
+        args.iter()
+            .flat_map(|arg| split_patterns(arg.as_ref()))
+            .map(|pattern| pattern.parse())
+            .collect()
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = CcuError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let regex = match s.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            Some(source) if s.len() > 1 => Regex::new(source),
+            _ => Regex::new(&glob_to_regex(s)?),
+        };
+
+        regex
+            .map(|regex| Self { regex })
+            .map_err(|e| CcuError::Pattern(format!("'{}': {}", s, e)))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Positional package filter: names must match at least one include pattern
+/// (if any are given) and none of the `!`-prefixed exclude patterns
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl Filter {
+    /// Parse filter arguments, e.g. `["serde*,tokio", "!tokio-*"]`
+    pub fn parse<S: AsRef<str>>(args: &[S]) -> Result<Self> {
+        let mut filter = Self::default();
+
+        for pattern in args.iter().flat_map(|arg| split_patterns(arg.as_ref())) {
+            match pattern.strip_prefix('!') {
+                Some(negated) => filter.exclude.push(negated.parse()?),
+                None => filter.include.push(pattern.parse()?),
+            }
+        }
+
+        Ok(filter)
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|p| p.matches(name));
+        let excluded = self.exclude.iter().any(|p| p.matches(name));
+
+        included && !excluded
+    }
+}
+
+/// Split a comma-separated pattern list, leaving commas inside `/regex/` alone
+fn split_patterns(arg: &str) -> Vec<&str> {
+    let mut patterns = Vec::new();
+    let mut start = 0;
+    let mut in_regex = false;
+    let mut escaped = false;
+
+    for (i, c) in arg.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_regex => escaped = true,
+            '/' if in_regex => in_regex = false,
+            '/' if arg[start..i].trim().trim_start_matches('!').is_empty() => in_regex = true,
+            ',' if !in_regex => {
+                patterns.push(arg[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    patterns.push(arg[start..].trim());
+
+    patterns.into_iter().filter(|p| !p.is_empty()).collect()
+}
+
+/// Translate a glob into an anchored regex. Supports `*`, `?`, character
+/// classes (`[abc]`, `[a-z]`, `[!abc]`) and backslash escapes.
+fn glob_to_regex(glob: &str) -> Result<String> {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::from("^");
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                regex.push_str(&regex::escape(&chars[i].to_string()));
+            }
+            '[' => {
+                let mut class = String::from("[");
+                i += 1;
+                if let Some('!' | '^') = chars.get(i) {
+                    class.push('^');
+                    i += 1;
+                }
+                // A leading ']' is a literal member of the class
+                if chars.get(i) == Some(&']') {
+                    class.push_str(r"\]");
+                    i += 1;
+                }
+
+                while i < chars.len() && chars[i] != ']' {
+                    if matches!(chars[i], '\\' | '[' | '&' | '~' | '^') {
+                        class.push('\\');
+                    }
+                    class.push(chars[i]);
+                    i += 1;
+                }
+
+                if i == chars.len() {
+                    return Err(CcuError::Pattern(format!("'{}': unclosed character class", glob)));
+                }
+                class.push(']');
+                regex.push_str(&class);
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+
+    regex.push('$');
+    Ok(regex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_matrix() {
+        let cases = [
+            // (pattern, name, expected)
+            ("serde", "serde", true),
+            ("serde", "serde_json", false),
+            ("serde*", "serde", true),
+            ("serde*", "serde_json", true),
+            ("serde*", "my-serde-helper", false),
+            ("*serde*", "my-serde-helper", true),
+            ("*-derive", "serde-derive", true),
+            ("*-derive", "serde_derive", false),
+            ("tokio-?", "tokio-a", true),
+            ("tokio-?", "tokio-ab", false),
+            ("tokio-?", "tokio-", false),
+            ("[ab]*", "anyhow", true),
+            ("[ab]*", "bytes", true),
+            ("[ab]*", "clap", false),
+            ("[!ab]*", "clap", true),
+            ("[!ab]*", "anyhow", false),
+            ("[^ab]*", "anyhow", false),
+            ("[a-c]lap", "clap", true),
+            ("[a-c]lap", "slap", false),
+            ("[]]x", "]x", true),
+            (r"a\*", "a*", true),
+            (r"a\*", "ab", false),
+            ("a.b", "a.b", true),
+            ("a.b", "axb", false),
+            ("a+b", "a+b", true),
+            ("/^serde/", "serde_json", true),
+            ("/^serde/", "my-serde", false),
+            ("/serde/", "my-serde", true),
+            ("/_(json|yaml)$/", "serde_yaml", true),
+            ("/_(json|yaml)$/", "serde_toml", false),
+            ("/a{2}/", "baab", true),
+        ];
+
+        for (pattern, name, expected) in cases {
+            let parsed: Pattern = pattern.parse().unwrap();
+            assert_eq!(
+                parsed.matches(name),
+                expected,
+                "pattern {:?} against {:?}",
+                pattern,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        for pattern in ["[abc", "/(/", "/[/"] {
+            assert!(pattern.parse::<Pattern>().is_err(), "{:?} should fail", pattern);
+        }
+    }
+
+    #[test]
+    fn split_matrix() {
+        let cases: [(&str, &[&str]); 7] = [
+            ("serde", &["serde"]),
+            ("serde*,tokio", &["serde*", "tokio"]),
+            (" serde , tokio ,", &["serde", "tokio"]),
+            ("/a{1,2}/,tokio", &["/a{1,2}/", "tokio"]),
+            ("!/a{1,2}/,tokio", &["!/a{1,2}/", "tokio"]),
+            (r"/a\/b,c/,d", &[r"/a\/b,c/", "d"]),
+            ("a/b,c", &["a/b", "c"]),
+        ];
+
+        for (arg, expected) in cases {
+            assert_eq!(split_patterns(arg), expected, "splitting {:?}", arg);
+        }
+    }
+
+    #[test]
+    fn filter_matrix() {
+        let cases: [(&[&str], &str, bool); 10] = [
+            (&[], "anything", true),
+            (&["serde*"], "serde_json", true),
+            (&["serde*"], "tokio", false),
+            (&["serde*,tokio"], "tokio", true),
+            (&["serde*", "tokio"], "tokio", true),
+            (&["!tokio-*"], "tokio", true),
+            (&["!tokio-*"], "tokio-util", false),
+            (&["tokio*", "!tokio-*"], "tokio", true),
+            (&["tokio*", "!tokio-*"], "tokio-util", false),
+            (&["tokio*,!/util/"], "tokio-stream", true),
+        ];
+
+        for (args, name, expected) in cases {
+            let filter = Filter::parse(args).unwrap();
+            assert_eq!(filter.matches(name), expected, "filter {:?} against {:?}", args, name);
+        }
+    }
+}
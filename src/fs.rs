@@ -0,0 +1,30 @@
+use crate::error::Result;
+use std::io::Write;
+use std::path::Path;
+
+/// Write a file by writing a temporary file next to it and renaming it into
+/// place, so a crash never leaves a half-written file behind
+pub fn write_atomic<P: AsRef<Path>>(path: P, contents: &str) -> Result<()> {
+    let path = path.as_ref();
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let tmp_path = dir.join(format!(".{}.ccu-{}.tmp", file_name, std::process::id()));
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+
+        // Keep the original file's permissions
+        if let Ok(metadata) = std::fs::metadata(path) {
+            std::fs::set_permissions(&tmp_path, metadata.permissions())?;
+        }
+
+        std::fs::rename(&tmp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    Ok(result?)
+}
@@ -0,0 +1,5 @@
+pub mod remote;
+pub mod repo;
+
+pub use remote::RemoteRefs;
+pub use repo::Repo;
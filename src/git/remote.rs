@@ -0,0 +1,106 @@
+use crate::error::{CcuError, Result};
+use regex::Regex;
+use semver::Version;
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Refs advertised by a git remote
+#[derive(Debug, Default, Clone)]
+pub struct RemoteRefs {
+    /// Commit the remote's HEAD (default branch) points to
+    pub head: Option<String>,
+    /// Branch name → commit
+    pub branches: HashMap<String, String>,
+    /// Tag name → commit, with annotated tags peeled to their commit
+    pub tags: HashMap<String, String>,
+}
+
+impl RemoteRefs {
+    /// List a remote's refs with the local `git` binary (`git ls-remote`)
+    pub fn fetch(url: &str) -> Result<Self> {
+        let output = Command::new("git")
+            .args(["ls-remote", "--", url])
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .map_err(|e| CcuError::Git(format!("Failed to run git: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(CcuError::Git(format!(
+                "git ls-remote {} failed: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Parse `git ls-remote` output ("<commit>\t<ref>" per line)
+    pub fn parse(output: &str) -> Self {
+        let mut refs = Self::default();
+        let mut peeled = HashMap::new();
+
+        for line in output.lines() {
+            let Some((commit, name)) = line.split_once('\t') else {
+                continue;
+            };
+            let commit = commit.trim().to_string();
+
+            if name == "HEAD" {
+                refs.head = Some(commit);
+            } else if let Some(branch) = name.strip_prefix("refs/heads/") {
+                refs.branches.insert(branch.to_string(), commit);
+            } else if let Some(tag) = name.strip_prefix("refs/tags/") {
+                match tag.strip_suffix("^{}") {
+                    Some(tag) => {
+                        peeled.insert(tag.to_string(), commit);
+                    }
+                    None => {
+                        refs.tags.insert(tag.to_string(), commit);
+                    }
+                }
+            }
+        }
+
+        // Annotated tags point at tag objects; use the commit they peel to
+        refs.tags.extend(peeled);
+        refs
+    }
+
+    /// Find the tag pointing at a commit, given a full or abbreviated hash
+    pub fn tag_for_commit(&self, rev: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .filter(|(_, commit)| !rev.is_empty() && commit.starts_with(rev))
+            .map(|(tag, _)| tag.as_str())
+            .max_by_key(|tag| tag_version(tag).map(|(_, version)| version))
+    }
+
+    /// Semver-looking tags sharing a prefix (e.g. "v", "serde-v")
+    pub fn versioned_tags<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a str, Version)> + 'a {
+        self.tags.keys().filter_map(move |tag| match tag_version(tag) {
+            Some((tag_prefix, version)) if tag_prefix == prefix => Some((tag.as_str(), version)),
+            _ => None,
+        })
+    }
+}
+
+/// Split a tag into its prefix and semver version, e.g. "serde-v1.2.3" →
+/// ("serde-v", 1.2.3). Returns `None` for tags that don't end in a version.
+pub fn tag_version(tag: &str) -> Option<(&str, Version)> {
+    static TAG_VERSION: OnceLock<Regex> = OnceLock::new();
+    let regex = TAG_VERSION.get_or_init(|| {
+        Regex::new(r"^(.*?v?)(\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?(?:\+[0-9A-Za-z.-]+)?)$").unwrap()
+    });
+
+    let captures = regex.captures(tag)?;
+    let prefix = captures.get(1)?.as_str();
+    // "foo1.2.3" isn't a version tag; the prefix must end at a separator or "v"
+    if !(prefix.is_empty() || prefix.ends_with(['v', 'V', '-', '_', '/', '@'])) {
+        return None;
+    }
+    let version = Version::parse(captures.get(2)?.as_str()).ok()?;
+
+    Some((prefix, version))
+}
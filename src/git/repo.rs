@@ -0,0 +1,89 @@
+use crate::error::{CcuError, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A local work tree, driven through the `git` binary
+#[derive(Debug, Clone)]
+pub struct Repo {
+    root: PathBuf,
+}
+
+impl Repo {
+    /// Find the work tree containing `path`
+    pub fn discover<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let dir = path.as_ref();
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        let root = run(dir, &["rev-parse", "--show-toplevel"])?;
+
+        Ok(Self {
+            root: PathBuf::from(root.trim()),
+        })
+    }
+
+    /// Whether tracked files have uncommitted changes, staged or not
+    pub fn is_dirty(&self) -> Result<bool> {
+        let status = run(&self.root, &["status", "--porcelain", "--untracked-files=no"])?;
+        Ok(!status.trim().is_empty())
+    }
+
+    /// Whether git tracks a file
+    pub fn is_tracked<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = absolute(path.as_ref());
+        run(&self.root, &["ls-files", "--error-unmatch", "--", &path]).is_ok()
+    }
+
+    /// A path relative to the work tree root, if it's inside the work tree
+    pub fn relative_path<P: AsRef<Path>>(&self, path: P) -> Option<PathBuf> {
+        let path = std::fs::canonicalize(path).ok()?;
+        let root = std::fs::canonicalize(&self.root).ok()?;
+        path.strip_prefix(root).ok().map(Path::to_path_buf)
+    }
+
+    /// Create a branch at HEAD and switch to it
+    pub fn create_branch(&self, name: &str) -> Result<()> {
+        run(&self.root, &["switch", "-c", name])?;
+        Ok(())
+    }
+
+    /// Commit the given files, and only them, with a message
+    pub fn commit<P: AsRef<Path>>(&self, paths: &[P], message: &str) -> Result<()> {
+        let paths: Vec<_> = paths.iter().map(|path| absolute(path.as_ref())).collect();
+
+        let mut add = vec!["add", "--"];
+        add.extend(paths.iter().map(String::as_str));
+        run(&self.root, &add)?;
+
+        let mut commit = vec!["commit", "-q", "-m", message, "--"];
+        commit.extend(paths.iter().map(String::as_str));
+        run(&self.root, &commit)?;
+        Ok(())
+    }
+}
+
+/// Paths are passed to git run from the work tree root, so make them absolute
+fn absolute(path: &Path) -> String {
+    std::fs::canonicalize(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Run git in `dir`, returning its stdout
+fn run(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| CcuError::Git(format!("Failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(CcuError::Git(format!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
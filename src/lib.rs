@@ -1,7 +1,17 @@
+pub mod advisory;
+pub mod bump;
 pub mod cargo;
+pub mod changelog;
 pub mod cli;
 pub mod commands;
+pub mod config;
+pub mod duplicates;
 pub mod error;
+pub mod filter;
+pub mod fs;
+pub mod git;
+pub mod license;
+pub mod peers;
 pub mod registry;
 pub mod ui;
 
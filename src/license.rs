@@ -0,0 +1,234 @@
+use crate::error::{CcuError, Result};
+use crate::registry::client::RegistryClient;
+use flate2::read::GzDecoder;
+use semver::Version;
+use std::fmt;
+use std::io::Read;
+
+/// The license a release declares in its `Cargo.toml`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum License {
+    /// An SPDX expression, e.g. "MIT OR Apache-2.0"
+    Spdx(String),
+    /// Only a `license-file`, with its path
+    File(String),
+    /// Neither `license` nor `license-file`
+    Unspecified,
+    /// Couldn't be read, e.g. because the download failed
+    Unknown,
+}
+
+impl License {
+    /// Normalize an SPDX expression, including the legacy "MIT/Apache-2.0" form
+    pub fn spdx(expression: &str) -> Self {
+        Self::Spdx(normalize(expression))
+    }
+
+    /// Read the license from the `Cargo.toml` at the root of a `.crate` archive
+    pub fn from_crate(archive: &[u8]) -> Result<Self> {
+        let mut tarball = tar::Archive::new(GzDecoder::new(archive));
+
+        for entry in tarball.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+            if path.split_once('/').map(|(_, file)| file) != Some("Cargo.toml") {
+                continue;
+            }
+
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            let manifest: toml::Table = toml::from_str(&content)?;
+            let package = manifest.get("package").and_then(|package| package.as_table());
+            let field = |name: &str| package?.get(name)?.as_str();
+
+            return Ok(match (field("license"), field("license-file")) {
+                (Some(expression), _) => Self::spdx(expression),
+                (None, Some(file)) => Self::File(file.to_string()),
+                (None, None) => Self::Unspecified,
+            });
+        }
+
+        Err(CcuError::RegistryQuery("the .crate archive has no Cargo.toml".to_string()))
+    }
+
+    /// A release's license from the registry's metadata, or from its
+    /// `.crate` archive when the registry only knows of a license file or
+    /// can't be reached
+    pub async fn fetch(client: &RegistryClient, name: &str, version: &Version) -> Result<Self> {
+        if let Ok(Some(expression)) = client.get_version_license(name, version).await {
+            return Ok(Self::spdx(&expression));
+        }
+        Self::from_crate(&client.download_crate(name, version).await?)
+    }
+
+    /// Whether an allowlist of SPDX expressions permits this license: either
+    /// an entry matches the whole expression, or the expression holds when
+    /// only the listed licenses are acceptable
+    pub fn is_allowed(&self, allowed: &[String]) -> bool {
+        let Self::Spdx(expression) = self else {
+            return false;
+        };
+        let allowed: Vec<String> = allowed.iter().map(|entry| normalize(entry)).collect();
+        if allowed.iter().any(|entry| entry.eq_ignore_ascii_case(expression)) {
+            return true;
+        }
+
+        let tokens = tokenize(expression);
+        let mut parser = ExpressionParser {
+            tokens: &tokens,
+            pos: 0,
+            allowed: &allowed,
+        };
+        parser.or().filter(|_| parser.pos == tokens.len()).unwrap_or(false)
+    }
+}
+
+impl fmt::Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Spdx(expression) => write!(f, "{}", expression),
+            Self::File(path) => write!(f, "see {}", path),
+            Self::Unspecified => write!(f, "unspecified"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+fn normalize(expression: &str) -> String {
+    tokenize(&expression.replace('/', " OR ")).join(" ").replace("( ", "(").replace(" )", ")")
+}
+
+fn tokenize(expression: &str) -> Vec<String> {
+    expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(|token| match token.to_ascii_uppercase().as_str() {
+            operator @ ("AND" | "OR" | "WITH") => operator.to_string(),
+            _ => token.to_string(),
+        })
+        .collect()
+}
+
+/// Evaluates an SPDX expression to whether it's acceptable, with the usual
+/// precedence: WITH binds tighter than AND, which binds tighter than OR
+struct ExpressionParser<'a> {
+    tokens: &'a [String],
+    pos: usize,
+    allowed: &'a [String],
+}
+
+impl ExpressionParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1).map(String::as_str)
+    }
+
+    fn or(&mut self) -> Option<bool> {
+        let mut value = self.and()?;
+        while self.peek() == Some("OR") {
+            self.pos += 1;
+            value |= self.and()?;
+        }
+        Some(value)
+    }
+
+    fn and(&mut self) -> Option<bool> {
+        let mut value = self.term()?;
+        while self.peek() == Some("AND") {
+            self.pos += 1;
+            value &= self.term()?;
+        }
+        Some(value)
+    }
+
+    fn term(&mut self) -> Option<bool> {
+        if self.peek() == Some("(") {
+            self.pos += 1;
+            let value = self.or()?;
+            return (self.next() == Some(")")).then_some(value);
+        }
+
+        let id = self.next().filter(|id| !matches!(*id, "AND" | "OR" | "WITH" | ")"))?.to_string();
+        let is_allowed = |id: &str| self.allowed.iter().any(|entry| entry.eq_ignore_ascii_case(id));
+
+        // An exception only grants extra permissions, so the license alone is enough
+        if self.peek() == Some("WITH") {
+            self.pos += 1;
+            let exception = self.next()?.to_string();
+            return Some(is_allowed(&id) || is_allowed(&format!("{} WITH {}", id, exception)));
+        }
+        Some(is_allowed(&id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_expressions() {
+        assert_eq!(License::spdx("MIT/Apache-2.0"), License::Spdx("MIT OR Apache-2.0".to_string()));
+        assert_eq!(
+            License::spdx(" (MIT  or Apache-2.0) AND Unicode-3.0"),
+            License::Spdx("(MIT OR Apache-2.0) AND Unicode-3.0".to_string())
+        );
+    }
+
+    fn crate_archive(manifest: &str) -> Vec<u8> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), Default::default()));
+        for (path, contents) in [("demo-1.0.0/Cargo.toml.orig", ""), ("demo-1.0.0/Cargo.toml", manifest)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn reads_license_from_crate() {
+        let cases = [
+            ("[package]\nname = \"demo\"\nlicense = \"MIT/Apache-2.0\"\n", License::spdx("MIT OR Apache-2.0")),
+            ("[package]\nname = \"demo\"\nlicense-file = \"LICENSE\"\n", License::File("LICENSE".to_string())),
+            ("[package]\nname = \"demo\"\n", License::Unspecified),
+        ];
+
+        for (manifest, expected) in cases {
+            assert_eq!(License::from_crate(&crate_archive(manifest)).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn allowlist_matrix() {
+        let allowed = ["MIT", "Apache-2.0", "GPL-3.0-only WITH Classpath-exception-2.0"].map(String::from);
+        let cases = [
+            ("MIT", true),
+            ("mit", true),
+            ("MIT OR Apache-2.0", true),
+            ("MIT/Apache-2.0", true),
+            ("BUSL-1.1", false),
+            ("MIT OR BUSL-1.1", true),
+            ("MIT AND BUSL-1.1", false),
+            ("(MIT OR BUSL-1.1) AND Apache-2.0", true),
+            ("(MIT AND BUSL-1.1) OR Unlicense", false),
+            ("Apache-2.0 WITH LLVM-exception", true),
+            ("GPL-3.0-only WITH Classpath-exception-2.0", true),
+            ("GPL-3.0-only", false),
+            ("MIT OR", false),
+            ("(MIT", false),
+        ];
+
+        for (expression, expected) in cases {
+            assert_eq!(License::spdx(expression).is_allowed(&allowed), expected, "license {:?}", expression);
+        }
+        assert!(!License::File("LICENSE".to_string()).is_allowed(&allowed));
+        assert!(!License::Unspecified.is_allowed(&allowed));
+        assert!(!License::Unknown.is_allowed(&allowed));
+    }
+}
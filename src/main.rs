@@ -1,28 +1,52 @@
 use cargo_check_updates::{
     cli::{Cli, Commands},
-    commands::CheckCommand,
+    commands::{ChangelogCommand, CheckCommand, UndoCommand},
+    ui::OutputFormat,
     Result,
 };
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser};
+use std::process::ExitCode;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let cli = Cli::parse();
 
+    let upgrades = cli.upgrade
+        || cli.diff
+        || cli.write_patch.is_some()
+        || cli.git_commit
+        || cli.git_commit_each
+        || cli.git_branch.is_some();
+    if cli.format == OutputFormat::Json && upgrades {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--format json only reports updates and can't be combined with -u, --diff, --write-patch or --git-*",
+            )
+            .exit();
+    }
+
     // Handle the command
     match cli.command {
         Some(Commands::Check { outdated: _ }) | None => {
             // Default command: check for updates
-            let check_cmd = CheckCommand::new(
-                cli.manifest_path,
-                cli.upgrade,
-                cli.interactive,
-                cli.filter,
-                cli.reject,
-            );
-            check_cmd.run().await?;
+            let check_cmd = CheckCommand::from_cli(&cli);
+            check_cmd.run().await
+        }
+        Some(Commands::Undo) => {
+            UndoCommand::new(cli.manifest_path).run()?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Commands::Changelog { name, from, to }) => {
+            let changelog_cmd = ChangelogCommand {
+                manifest_path: cli.manifest_path,
+                name,
+                from,
+                to,
+                config_path: cli.config,
+            };
+            changelog_cmd.run().await?;
+            Ok(ExitCode::SUCCESS)
         }
     }
-
-    Ok(())
 }
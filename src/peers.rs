@@ -0,0 +1,254 @@
+use crate::registry::client::VersionInfo;
+use semver::{Version, VersionReq};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A direct dependency whose upgrade may depend on the others'
+#[derive(Debug, Clone)]
+pub struct Peer<'v> {
+    /// Registry name
+    pub name: String,
+    /// Version in use: the locked one, or the requirement's without a lockfile
+    pub installed: Version,
+    /// Version we'd upgrade to, or `installed`
+    pub planned: Version,
+    /// Every published version, from the registry index
+    pub versions: &'v [VersionInfo],
+    /// Versions the dependency's policy allows upgrading to (cooldown, MSRV,
+    /// features, rules...); a lowered upgrade is picked from these
+    pub allowed: Vec<Version>,
+}
+
+/// Why a peer's planned upgrade was lowered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerConstraint {
+    /// `version` of this crate needs `peer` at `req`, and the peer won't get there
+    Needs { version: Version, peer: String, req: VersionReq },
+    /// `peer` needs this crate at `req`, which `version` doesn't satisfy
+    RequiredBy { version: Version, peer: String, req: VersionReq },
+}
+
+impl fmt::Display for PeerConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Needs { version, peer, req } => write!(f, "{} needs {} {}", version, peer, req),
+            Self::RequiredBy { version, peer, req } => write!(f, "{} held back: {} needs {}", version, peer, req),
+        }
+    }
+}
+
+impl<'v> Peer<'v> {
+    fn info(&self, version: &Version) -> Option<&'v VersionInfo> {
+        self.versions.iter().find(|info| &info.version == version)
+    }
+
+    /// Newest allowed version from `installed` up to, but excluding,
+    /// `planned` that satisfies a predicate
+    fn newest_below_planned(&self, accept: impl Fn(&Version) -> bool) -> Option<Version> {
+        self.allowed
+            .iter()
+            .chain([&self.installed])
+            .filter(|version| **version >= self.installed && **version < self.planned && accept(version))
+            .max()
+            .cloned()
+    }
+}
+
+/// Requirements a release places on other crates, by registry name; dev and
+/// optional dependencies don't tie crates together
+fn requirements(info: &VersionInfo) -> impl Iterator<Item = (&str, VersionReq)> {
+    info.deps
+        .iter()
+        .filter(|dep| !dep.optional && dep.kind.as_deref() != Some("dev"))
+        .filter_map(|dep| {
+            let req = VersionReq::parse(&dep.req).ok()?;
+            Some((dep.package.as_deref().unwrap_or(&dep.name), req))
+        })
+}
+
+/// Lower planned versions until every planned release's requirements on the
+/// other peers hold, and return why each lowered peer was lowered.
+///
+/// When a release needs a newer peer than the peer is planned to get, the
+/// release is replaced by the newest one that works with the planned peers.
+/// When it needs an older peer, the peer's upgrade is lowered instead. Pairs
+/// that already disagree before any upgrade are left alone.
+pub fn resolve(peers: &mut [Peer]) -> Vec<Option<PeerConstraint>> {
+    let mut notes = vec![None; peers.len()];
+    let mut index = HashMap::new();
+    for (i, peer) in peers.iter().enumerate() {
+        index.entry(peer.name.clone()).or_insert(i);
+    }
+
+    // Every change lowers a planned version, so this ends
+    'scan: loop {
+        for a in 0..peers.len() {
+            let Some(info) = peers[a].info(&peers[a].planned) else {
+                continue;
+            };
+
+            for (name, req) in requirements(info) {
+                let Some(&b) = index.get(name) else {
+                    continue;
+                };
+                if b == a || req.matches(&peers[b].planned) {
+                    continue;
+                }
+
+                let b_too_old = peers[b]
+                    .versions
+                    .iter()
+                    .any(|info| info.version > peers[b].planned && req.matches(&info.version));
+
+                if b_too_old && peers[a].planned > peers[a].installed {
+                    let compatible = |version: &Version| {
+                        peers[a].info(version).is_some_and(|info| {
+                            requirements(info).all(|(name, req)| {
+                                index
+                                    .get(name)
+                                    .is_none_or(|&peer| peer == a || req.matches(&peers[peer].planned))
+                            })
+                        })
+                    };
+                    let lowered = peers[a]
+                        .newest_below_planned(compatible)
+                        .unwrap_or_else(|| peers[a].installed.clone());
+                    let constraint = PeerConstraint::Needs {
+                        version: peers[a].planned.clone(),
+                        peer: peers[b].name.clone(),
+                        req,
+                    };
+
+                    notes[a].get_or_insert(constraint);
+                    peers[a].planned = lowered;
+                    continue 'scan;
+                }
+
+                if !b_too_old && peers[b].planned > peers[b].installed {
+                    if let Some(lowered) = peers[b].newest_below_planned(|version| req.matches(version)) {
+                        let constraint = PeerConstraint::RequiredBy {
+                            version: peers[b].planned.clone(),
+                            peer: peers[a].name.clone(),
+                            req,
+                        };
+
+                        notes[b].get_or_insert(constraint);
+                        peers[b].planned = lowered;
+                        continue 'scan;
+                    }
+                }
+            }
+        }
+
+        return notes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Releases of a crate as (version, [(dependency, requirement)])
+    fn releases(list: &[(&str, &[(&str, &str)])]) -> Vec<VersionInfo> {
+        list.iter()
+            .map(|(version, deps)| VersionInfo::with_deps(Version::parse(version).unwrap(), deps))
+            .collect()
+    }
+
+    fn peer<'v>(name: &str, installed: &str, planned: &str, versions: &'v [VersionInfo]) -> Peer<'v> {
+        Peer {
+            name: name.to_string(),
+            installed: Version::parse(installed).unwrap(),
+            planned: Version::parse(planned).unwrap(),
+            versions,
+            allowed: versions.iter().map(|info| info.version.clone()).collect(),
+        }
+    }
+
+    fn planned(peers: &[Peer]) -> Vec<String> {
+        peers.iter().map(|peer| peer.planned.to_string()).collect()
+    }
+
+    #[test]
+    fn lowers_a_release_that_needs_a_held_back_peer() {
+        let axum = releases(&[
+            ("0.6.0", &[("hyper", "^0.14")]),
+            ("0.6.20", &[("hyper", "^0.14")]),
+            ("0.7.0", &[("hyper", "^1.0")]),
+        ]);
+        let hyper = releases(&[("0.14.0", &[]), ("0.14.28", &[]), ("1.0.0", &[])]);
+
+        // hyper is held at 0.14 by its policy
+        let mut peers = [peer("axum", "0.6.0", "0.7.0", &axum), peer("hyper", "0.14.0", "0.14.28", &hyper)];
+        let notes = resolve(&mut peers);
+        assert_eq!(planned(&peers), ["0.6.20", "0.14.28"]);
+        assert_eq!(notes[0].as_ref().unwrap().to_string(), "0.7.0 needs hyper ^1.0");
+        assert_eq!(notes[1], None);
+
+        // Both move together when both may
+        let mut peers = [peer("axum", "0.6.0", "0.7.0", &axum), peer("hyper", "0.14.0", "1.0.0", &hyper)];
+        assert_eq!(resolve(&mut peers), [None, None]);
+        assert_eq!(planned(&peers), ["0.7.0", "1.0.0"]);
+    }
+
+    #[test]
+    fn lowers_a_peer_a_dependent_cant_use_yet() {
+        let tower_http = releases(&[("0.4.0", &[("http", "^0.2")]), ("0.4.4", &[("http", "^0.2")])]);
+        let http = releases(&[("0.2.0", &[]), ("0.2.12", &[]), ("1.1.0", &[])]);
+
+        let mut peers = [peer("tower-http", "0.4.0", "0.4.4", &tower_http), peer("http", "0.2.0", "1.1.0", &http)];
+        let notes = resolve(&mut peers);
+        assert_eq!(planned(&peers), ["0.4.4", "0.2.12"]);
+        assert_eq!(notes[1].as_ref().unwrap().to_string(), "1.1.0 held back: tower-http needs ^0.2");
+    }
+
+    #[test]
+    fn lowers_only_to_versions_the_policy_allows() {
+        let tower_http = releases(&[("0.4.0", &[("http", "^0.2")]), ("0.4.4", &[("http", "^0.2")])]);
+        let http = releases(&[("0.2.0", &[]), ("0.2.9", &[]), ("0.2.12", &[]), ("1.1.0", &[])]);
+
+        // e.g. 0.2.12 is younger than the cooldown
+        let mut http_peer = peer("http", "0.2.0", "1.1.0", &http);
+        http_peer.allowed.retain(|version| version.to_string() != "0.2.12");
+        let mut peers = [peer("tower-http", "0.4.0", "0.4.4", &tower_http), http_peer];
+        resolve(&mut peers);
+        assert_eq!(planned(&peers), ["0.4.4", "0.2.9"]);
+
+        // A release that needs a held-back peer falls back to the installed
+        // version when the policy rules out everything in between
+        let axum = releases(&[
+            ("0.6.0", &[("http", "^0.2")]),
+            ("0.6.20", &[("http", "^0.2")]),
+            ("0.7.0", &[("http", "^1")]),
+        ]);
+        let mut axum_peer = peer("axum", "0.6.0", "0.7.0", &axum);
+        axum_peer.allowed.retain(|version| version.to_string() != "0.6.20");
+        let mut peers = [axum_peer, peer("http", "0.2.0", "0.2.12", &http)];
+        resolve(&mut peers);
+        assert_eq!(planned(&peers), ["0.6.0", "0.2.12"]);
+    }
+
+    #[test]
+    fn leaves_existing_mismatches_and_unrelated_crates_alone() {
+        let a = releases(&[("1.0.0", &[("b", "^2.0"), ("serde", "^1")]), ("1.1.0", &[("b", "^2.0")])]);
+        let b = releases(&[("1.0.0", &[]), ("1.5.0", &[])]);
+
+        let mut peers = [peer("a", "1.0.0", "1.0.0", &a), peer("b", "1.0.0", "1.5.0", &b)];
+        assert_eq!(resolve(&mut peers), [None, None]);
+        assert_eq!(planned(&peers), ["1.0.0", "1.5.0"]);
+    }
+
+    #[test]
+    fn cascades_through_chains() {
+        // c's newest needs the newest b, whose newest needs a release of a that's held back
+        let a = releases(&[("1.0.0", &[]), ("2.0.0", &[])]);
+        let b = releases(&[("1.0.0", &[("a", "^1")]), ("2.0.0", &[("a", "^2")])]);
+        let c = releases(&[("1.0.0", &[("b", "^1")]), ("1.1.0", &[("b", "^1")]), ("2.0.0", &[("b", "^2")])]);
+
+        let mut peers = [peer("a", "1.0.0", "1.0.0", &a), peer("b", "1.0.0", "2.0.0", &b), peer("c", "1.0.0", "2.0.0", &c)];
+        let notes = resolve(&mut peers);
+        assert_eq!(planned(&peers), ["1.0.0", "1.0.0", "1.1.0"]);
+        assert_eq!(notes[1].as_ref().unwrap().to_string(), "2.0.0 needs a ^2");
+        assert_eq!(notes[2].as_ref().unwrap().to_string(), "2.0.0 needs b ^2");
+    }
+}
@@ -1,9 +1,13 @@
 use crate::error::Result;
+use chrono::{DateTime, Utc};
 use reqwest::Client;
 use semver::Version;
 use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 const CRATES_IO_API: &str = "https://crates.io/api/v1";
+const CRATES_IO_INDEX: &str = "https://index.crates.io";
+const CRATES_IO_DOWNLOAD: &str = "https://static.crates.io/crates";
 
 #[derive(Deserialize, Debug)]
 struct CrateResponse {
@@ -16,19 +20,172 @@ struct CrateInfo {
     newest_version: String,
 }
 
+#[derive(Deserialize, Debug)]
+struct VersionResponse {
+    version: VersionMetadata,
+}
+
+#[derive(Deserialize, Debug)]
+struct VersionMetadata {
+    license: Option<String>,
+}
+
+/// A single published version, as recorded in the registry index
+#[derive(Deserialize, Debug, Clone)]
+pub struct VersionInfo {
+    #[serde(rename = "vers")]
+    pub version: Version,
+    #[serde(default)]
+    pub yanked: bool,
+    /// Publish time; missing for versions published before the index recorded it
+    #[serde(default)]
+    pub pubtime: Option<DateTime<Utc>>,
+    /// Minimum supported Rust version declared by this release
+    #[serde(default)]
+    pub rust_version: Option<String>,
+    /// Feature table; features using newer syntax (`dep:`, `?/`) are in `features2`
+    #[serde(default)]
+    pub features: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub features2: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub deps: Vec<IndexDependency>,
+}
+
+/// A dependency of a published version, as recorded in the registry index
+#[derive(Deserialize, Debug, Clone)]
+pub struct IndexDependency {
+    /// Name the crate uses for the dependency, which is the registry name unless renamed
+    pub name: String,
+    pub req: String,
+    #[serde(default)]
+    pub optional: bool,
+    /// "normal", "dev" or "build"
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// Registry name of a renamed dependency
+    #[serde(default)]
+    pub package: Option<String>,
+}
+
+impl VersionInfo {
+    /// A version known only by its number, e.g. one taken from a git tag
+    pub fn new(version: Version) -> Self {
+        Self {
+            version,
+            yanked: false,
+            pubtime: None,
+            rust_version: None,
+            features: HashMap::new(),
+            features2: HashMap::new(),
+            deps: Vec::new(),
+        }
+    }
+
+    /// A release with normal dependencies as (registry name, requirement),
+    /// and nothing else known about it
+    pub fn with_deps(version: Version, deps: &[(&str, &str)]) -> Self {
+        let mut info = Self::new(version);
+        info.deps = deps
+            .iter()
+            .map(|(name, req)| IndexDependency {
+                name: name.to_string(),
+                req: req.to_string(),
+                optional: false,
+                kind: None,
+                package: None,
+            })
+            .collect();
+        info
+    }
+
+    /// Names of all features this release offers, including the implicit
+    /// ones optional dependencies get unless some feature refers to them as `dep:name`
+    pub fn feature_names(&self) -> BTreeSet<&str> {
+        let explicit = self.features.iter().chain(&self.features2);
+        let mut names: BTreeSet<&str> = explicit.clone().map(|(name, _)| name.as_str()).collect();
+
+        let referenced: HashSet<&str> = explicit
+            .flat_map(|(_, enables)| enables)
+            .filter_map(|enable| enable.strip_prefix("dep:"))
+            .collect();
+        names.extend(
+            self.deps
+                .iter()
+                .filter(|dep| dep.optional && !referenced.contains(dep.name.as_str()))
+                .map(|dep| dep.name.as_str()),
+        );
+
+        names
+    }
+
+    /// Features enabled by `default`
+    pub fn default_features(&self) -> Vec<&str> {
+        self.features
+            .get("default")
+            .or_else(|| self.features2.get("default"))
+            .map_or_else(Vec::new, |enables| enables.iter().map(String::as_str).collect())
+    }
+
+    /// Enabled features this release doesn't offer. Entries naming a feature of
+    /// a nested dependency (`dep/feature`) aren't checked.
+    pub fn missing_features<'f>(&self, enabled: &'f [String]) -> Vec<&'f str> {
+        let names = self.feature_names();
+        enabled
+            .iter()
+            .map(String::as_str)
+            .filter(|feature| !feature.contains('/') && !names.contains(feature))
+            .collect()
+    }
+
+    /// Parsed `rust_version`, if the release declares one
+    pub fn rust_version(&self) -> Option<Version> {
+        self.rust_version.as_deref().and_then(crate::cargo::parser::parse_rust_version)
+    }
+}
+
 #[derive(Clone)]
 pub struct RegistryClient {
     client: Client,
+    index_url: String,
+    /// Base URL `.crate` files are downloaded from
+    download_url: String,
+    /// Web API with per-version metadata; only crates.io has a known one
+    api_url: Option<String>,
 }
 
 impl RegistryClient {
     pub fn new() -> Self {
+        Self::with_index_url(CRATES_IO_INDEX)
+    }
+
+    /// Create a client that reads from a sparse index other than crates.io
+    pub fn with_index_url(index_url: &str) -> Self {
         let client = Client::builder()
             .user_agent("cargo-check-updates")
             .build()
             .expect("Failed to create HTTP client");
 
-        Self { client }
+        let index_url = index_url.trim_end_matches('/').to_string();
+        Self {
+            client,
+            api_url: (index_url == CRATES_IO_INDEX).then(|| CRATES_IO_API.to_string()),
+            index_url,
+            download_url: CRATES_IO_DOWNLOAD.to_string(),
+        }
+    }
+
+    /// Read per-version metadata from another crates.io-compatible web API
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = Some(api_url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Download `.crate` files from a mirror; they're fetched from
+    /// `<url>/<name>/<name>-<version>.crate`
+    pub fn with_download_url(mut self, download_url: &str) -> Self {
+        self.download_url = download_url.trim_end_matches('/').to_string();
+        self
     }
 
     /// Get the latest version of a crate from crates.io
@@ -56,10 +213,107 @@ impl RegistryClient {
         Ok(version)
     }
 
-    /// Get all versions of a crate from crates.io
-    pub async fn get_all_versions(&self, _crate_name: &str) -> Result<Vec<Version>> {
-        // TODO: Implement actual API call
-        Ok(vec![])
+    /// License expression the registry's web API records for a release;
+    /// `None` without an API, or when the release only has a license file
+    pub async fn get_version_license(&self, crate_name: &str, version: &Version) -> Result<Option<String>> {
+        let Some(api_url) = &self.api_url else {
+            return Ok(None);
+        };
+        let url = format!("{}/crates/{}/{}", api_url, crate_name, version);
+
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(crate::error::CcuError::RegistryQuery(format!(
+                "Failed to fetch metadata of {} {}: HTTP {}",
+                crate_name,
+                version,
+                response.status()
+            )));
+        }
+
+        let version_response: VersionResponse = response.json().await?;
+        Ok(version_response.version.license)
+    }
+
+    /// Download the `.crate` archive of a release, or read it from Cargo's
+    /// download cache if Cargo already fetched it from crates.io
+    pub async fn download_crate(&self, crate_name: &str, version: &Version) -> Result<Vec<u8>> {
+        if let Some(archive) = self.cached_crate(crate_name, version) {
+            return Ok(archive);
+        }
+
+        let url = format!("{}/{}/{}-{}.crate", self.download_url, crate_name, crate_name, version);
+
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            return Err(crate::error::CcuError::RegistryQuery(format!(
+                "Failed to download {} {}: HTTP {}",
+                crate_name,
+                version,
+                response.status()
+            )));
+        }
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    /// Get all published versions of a crate from the sparse registry index
+    pub async fn get_all_versions(&self, crate_name: &str) -> Result<Vec<VersionInfo>> {
+        let url = format!("{}/{}", self.index_url, Self::index_path(crate_name));
+
+        let response = self.client.get(&url).send().await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(crate::error::CcuError::RegistryQuery(format!(
+                "'{}' is not in the registry",
+                crate_name
+            )));
+        }
+        if !response.status().is_success() {
+            return Err(crate::error::CcuError::RegistryQuery(format!(
+                "Failed to fetch index entry for '{}': HTTP {}",
+                crate_name,
+                response.status()
+            )));
+        }
+
+        // The index file holds one JSON object per line, one per version.
+        // Lines we can't parse (e.g. non-semver versions) are skipped.
+        let body = response.text().await?;
+        let versions = body
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| serde_json::from_str::<VersionInfo>(line).ok())
+            .collect();
+
+        Ok(versions)
+    }
+
+    /// A `.crate` file from `$CARGO_HOME/registry/cache/index.crates.io-*`
+    fn cached_crate(&self, crate_name: &str, version: &Version) -> Option<Vec<u8>> {
+        if self.index_url != CRATES_IO_INDEX {
+            return None;
+        }
+
+        let file_name = format!("{}-{}.crate", crate_name, version);
+        std::fs::read_dir(crate::cargo::home()?.join("registry").join("cache"))
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("index.crates.io-"))
+            .find_map(|entry| std::fs::read(entry.path().join(&file_name)).ok())
+    }
+
+    /// Path of a crate's file within the index, e.g. "se/rd/serde"
+    fn index_path(crate_name: &str) -> String {
+        let name = crate_name.to_lowercase();
+        match name.len() {
+            1 => format!("1/{}", name),
+            2 => format!("2/{}", name),
+            3 => format!("3/{}/{}", &name[..1], name),
+            _ => format!("{}/{}/{}", &name[..2], &name[2..4], name),
+        }
     }
 }
 
@@ -68,3 +322,33 @@ impl Default for RegistryClient {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_names_from_index_line() {
+        let line = r#"{"name":"demo","vers":"1.0.0","cksum":"","yanked":false,
+            "features":{"default":["std"],"std":[],"json":["serde_json"]},
+            "features2":{"tls":["dep:rustls","http?/tls"]},
+            "deps":[
+                {"name":"serde_json","req":"^1","optional":true,"kind":"normal"},
+                {"name":"rustls","req":"^0.23","optional":true,"kind":"normal"},
+                {"name":"cc","req":"^1","optional":true,"kind":"build"},
+                {"name":"http","req":"^1","optional":false,"kind":"normal"}
+            ]}"#
+            .replace('\n', "");
+        let info: VersionInfo = serde_json::from_str(&line).unwrap();
+
+        // Optional dependencies are implicit features unless referenced as `dep:`
+        assert_eq!(
+            info.feature_names().into_iter().collect::<Vec<_>>(),
+            ["cc", "default", "json", "serde_json", "std", "tls"]
+        );
+        assert_eq!(info.default_features(), ["std"]);
+
+        let enabled = ["json", "rustls", "tls", "http/tls"].map(str::to_string);
+        assert_eq!(info.missing_features(&enabled), ["rustls"]);
+    }
+}
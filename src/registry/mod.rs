@@ -1,3 +1,3 @@
 pub mod client;
 
-pub use client::RegistryClient;
+pub use client::{RegistryClient, VersionInfo};
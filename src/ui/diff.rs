@@ -0,0 +1,63 @@
+use crate::git::Repo;
+use colored::Colorize;
+use similar::TextDiff;
+use std::io::IsTerminal;
+use std::path::Path;
+
+/// Build a unified diff between two versions of a file, with `a/` and `b/`
+/// headers so the result can be applied with `git apply`
+pub fn unified_diff(old: &str, new: &str, path: &str) -> String {
+    let path = path.trim_start_matches("./");
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string()
+}
+
+/// Path to name a file by in a patch: relative to the root of its git work
+/// tree, which is where `git apply` resolves paths, or else to the current
+/// directory
+pub fn patch_path(path: &str) -> String {
+    let file = Path::new(path);
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let relative = match Repo::discover(dir) {
+        Ok(repo) => repo.relative_path(file),
+        Err(_) => std::env::current_dir()
+            .and_then(std::fs::canonicalize)
+            .ok()
+            .zip(std::fs::canonicalize(file).ok())
+            .and_then(|(cwd, file)| file.strip_prefix(cwd).ok().map(Path::to_path_buf)),
+    };
+
+    match relative {
+        Some(relative) => relative
+            .iter()
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        None => path.to_string(),
+    }
+}
+
+/// Print a diff, coloured when stdout is a terminal and plain when piped
+pub fn print_diff(patch: &str) {
+    if !std::io::stdout().is_terminal() {
+        print!("{}", patch);
+        return;
+    }
+
+    for line in patch.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            println!("{}", line.bold());
+        } else if line.starts_with('+') {
+            println!("{}", line.green());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else if line.starts_with("@@") {
+            println!("{}", line.cyan());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod diff;
 pub mod output;
 
-pub use output::OutputFormatter;
+pub use output::{OutputFormat, OutputFormatter, SortKey};
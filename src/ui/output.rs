@@ -1,10 +1,37 @@
-use colored::Colorize;
+use crate::bump::BumpKind;
+use crate::changelog::ReleaseNotes;
+use colored::{ColoredString, Colorize};
 use semver::Version;
 
+/// How check results are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// One line per dependency
+    #[default]
+    Table,
+    /// Blocks by kind of update, with a summary and a legend
+    Group,
+    /// A JSON document on stdout, for scripts
+    Json,
+}
+
+/// Order of dependencies in the report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    /// Most libyear drift first
+    Age,
+    /// Alphabetically
+    Name,
+    /// Breaking updates first
+    Bump,
+    /// By manifest section
+    Section,
+}
+
 pub struct OutputFormatter;
 
 impl OutputFormatter {
-    /// Format a version update with color coding based on semver
+    /// Format a version update with color coding based on the kind of bump
     pub fn format_update(
         crate_name: &str,
         current: &Version,
@@ -12,26 +39,25 @@ impl OutputFormatter {
     ) -> String {
         let arrow = "→";
 
-        let colored_latest = if latest.major > current.major {
-            // Major version update - red
-            latest.to_string().red().bold()
-        } else if latest.minor > current.minor {
-            // Minor version update - cyan
-            latest.to_string().cyan()
-        } else {
-            // Patch version update - green
-            latest.to_string().green()
-        };
-
         format!(
             "{:<30} {} {} {}",
             crate_name.bold(),
             current,
             arrow,
-            colored_latest
+            Self::paint(BumpKind::of(current, latest), &latest.to_string())
         )
     }
 
+    /// Color text by the kind of bump it describes
+    pub fn paint(kind: BumpKind, text: &str) -> ColoredString {
+        match kind {
+            BumpKind::Breaking => text.red().bold(),
+            BumpKind::CompatibleFeature => text.cyan(),
+            BumpKind::CompatibleFix => text.green(),
+            BumpKind::Prerelease => text.magenta(),
+        }
+    }
+
     /// Print a summary header
     pub fn print_header(total: usize, outdated: usize) {
         println!("\n{}", "Dependency Check Results".bold().underline());
@@ -39,11 +65,36 @@ impl OutputFormatter {
         println!("Outdated dependencies: {}\n", outdated.to_string().yellow());
     }
 
+    /// Print the changelog entries between two versions of a crate
+    pub fn print_release_notes(crate_name: &str, from: &Version, to: &Version, notes: &ReleaseNotes) {
+        println!("{}", format!("{} {} → {}", crate_name, from, to).bold().underline());
+
+        match notes {
+            ReleaseNotes::Sections { path, sections } => {
+                println!("{}\n", format!("from {}", path).dimmed());
+                for section in sections {
+                    println!("{}\n", section.text);
+                }
+            }
+            ReleaseNotes::Added { path, text } => {
+                println!("{}\n", format!("lines added to {} since {}", path, from).dimmed());
+                println!("{}\n", text);
+            }
+            ReleaseNotes::Nothing { path } => {
+                println!("{}\n", format!("{} has no entries for these releases", path).yellow());
+            }
+            ReleaseNotes::Missing => {
+                println!("{}\n", format!("{} {} ships no changelog", crate_name, to).yellow());
+            }
+        }
+    }
+
     /// Print update legend
     pub fn print_legend() {
         println!("\n{}", "Legend:".bold());
-        println!("  {} Major version update", "Red".red());
-        println!("  {} Minor version update", "Cyan".cyan());
-        println!("  {} Patch version update", "Green".green());
+        println!("  {} Breaking change (0.x minor and 0.0.x patch bumps included)", "Red".red());
+        println!("  {} Compatible, new features", "Cyan".cyan());
+        println!("  {} Compatible, fixes only", "Green".green());
+        println!("  {} Prerelease", "Magenta".magenta());
     }
 }
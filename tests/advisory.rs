@@ -0,0 +1,80 @@
+//! Matching versions against a local advisory-db clone.
+
+use cargo_check_updates::advisory::{Advisory, AdvisoryDb};
+use semver::Version;
+use std::path::Path;
+
+fn db() -> AdvisoryDb {
+    AdvisoryDb::open(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/advisory-db")).unwrap()
+}
+
+fn ids(advisories: &[&Advisory]) -> Vec<String> {
+    advisories.iter().map(|advisory| advisory.id.clone()).collect()
+}
+
+#[test]
+fn reads_markdown_and_toml_advisories() {
+    let db = db();
+    let demo = db.for_crate("demo");
+    assert_eq!(demo.len(), 2);
+
+    let markdown = demo.iter().find(|advisory| advisory.id == "RUSTSEC-2024-0001").unwrap();
+    assert_eq!(markdown.title, "Buffer overflow in `demo::parse`");
+    assert_eq!(markdown.aliases, ["CVE-2024-0001"]);
+    assert!(markdown.is_vulnerability());
+
+    let toml = demo.iter().find(|advisory| advisory.id == "RUSTSEC-2023-0002").unwrap();
+    assert_eq!(toml.title, "Old advisory that was withdrawn");
+    assert!(toml.withdrawn);
+
+    assert_eq!(db.for_crate("tidy")[0].informational.as_deref(), Some("unmaintained"));
+    assert!(db.for_crate("other").is_empty());
+}
+
+#[test]
+fn skips_unparsable_advisories() {
+    let db = db();
+    let unparsable: Vec<_> = db
+        .unparsable
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+
+    assert_eq!(unparsable, ["RUSTSEC-2024-0004.md"]);
+    assert_eq!(db.for_crate("tidy").len(), 1);
+}
+
+#[test]
+fn matches_affected_versions() {
+    let db = db();
+    let cases = [
+        ("1.1.9", false),
+        ("1.2.0", true),
+        ("1.4.1", true),
+        ("1.4.2", false),
+        ("2.0.0", true),
+        ("2.1.0", false),
+    ];
+
+    for (version, affected) in cases {
+        let found = ids(&db.vulnerabilities("demo", &Version::parse(version).unwrap()));
+        assert_eq!(!found.is_empty(), affected, "demo {}: {:?}", version, found);
+    }
+
+    // Informational notices aren't vulnerabilities
+    assert!(db.vulnerabilities("tidy", &Version::new(0, 1, 0)).is_empty());
+}
+
+#[test]
+fn matches_informational_notices() {
+    let db = db();
+    assert_eq!(ids(&db.notices("tidy", &Version::new(0, 1, 0))), ["RUSTSEC-2024-0003"]);
+    assert!(db.notices("demo", &Version::new(1, 3, 0)).is_empty());
+}
+
+#[test]
+fn rejects_malformed_advisories() {
+    assert!(Advisory::parse("```toml\n[advisory]\nid = \"X\"\n").is_err());
+    assert!(Advisory::parse("[advisory]\nid = \"X\"\n").is_err());
+    assert!(AdvisoryDb::open("/nonexistent/advisory-db").is_err());
+}
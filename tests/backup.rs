@@ -0,0 +1,146 @@
+//! Backing up manifests before an upgrade and restoring them with `ccu undo`.
+
+use cargo_check_updates::cargo::backup::Backup;
+use cargo_check_updates::commands::UndoCommand;
+use cargo_check_updates::CcuError;
+use std::path::{Path, PathBuf};
+
+const LOCKFILE: &str = "version = 3\n";
+
+/// A fresh workspace with a Cargo.lock at its root and a member per name
+fn workspace(name: &str, members: &[&str]) -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("Cargo.lock"), LOCKFILE).unwrap();
+
+    members
+        .iter()
+        .map(|member| {
+            let dir = root.join(member);
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("Cargo.toml");
+            std::fs::write(&path, manifest(member, "1.0")).unwrap();
+            path
+        })
+        .collect()
+}
+
+fn manifest(name: &str, serde: &str) -> String {
+    format!("[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"{}\"\n", name, serde)
+}
+
+fn read(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn restores_the_manifest_and_lockfile() {
+    let [manifest_path] = &workspace("backup-restore", &["app"])[..] else {
+        unreachable!()
+    };
+    let lockfile = manifest_path.parent().unwrap().parent().unwrap().join("Cargo.lock");
+
+    let backup = Backup::create(manifest_path).unwrap();
+    assert_eq!(backup.manifest, std::fs::canonicalize(manifest_path).unwrap());
+    assert_eq!(backup.files.len(), 2);
+    assert!(backup.dir.starts_with(Backup::root_for_manifest(manifest_path).unwrap()));
+
+    std::fs::write(manifest_path, manifest("app", "1.0.200")).unwrap();
+    std::fs::write(&lockfile, "version = 4\n").unwrap();
+
+    let latest = Backup::latest(manifest_path).unwrap().unwrap();
+    assert_eq!(latest.dir, backup.dir);
+    let restored = latest.restore().unwrap();
+    assert_eq!(restored.len(), 2);
+    assert_eq!(read(manifest_path), manifest("app", "1.0"));
+    assert_eq!(read(&lockfile), LOCKFILE);
+
+    // Restoring deletes the backup
+    assert!(!backup.dir.exists());
+    assert!(Backup::latest(manifest_path).unwrap().is_none());
+}
+
+#[test]
+fn workspace_members_only_see_their_own_backups() {
+    let [a, b] = &workspace("backup-members", &["a", "b"])[..] else {
+        unreachable!()
+    };
+    assert_eq!(Backup::root_for_manifest(a).unwrap(), Backup::root_for_manifest(b).unwrap());
+
+    let first = Backup::create(a).unwrap();
+    std::fs::write(a, manifest("a", "1.0.100")).unwrap();
+    let second = Backup::create(a).unwrap();
+    std::fs::write(a, manifest("a", "1.0.200")).unwrap();
+    // The newest backup in the shared root belongs to b
+    let other = Backup::create(b).unwrap();
+    assert_ne!(first.dir, second.dir);
+
+    assert_eq!(Backup::latest(a).unwrap().unwrap().dir, second.dir);
+    assert_eq!(Backup::latest(b).unwrap().unwrap().dir, other.dir);
+
+    // Each undo goes one of a's runs further back, leaving b's backup alone
+    Backup::latest(a).unwrap().unwrap().restore().unwrap();
+    assert_eq!(read(a), manifest("a", "1.0.100"));
+    Backup::latest(a).unwrap().unwrap().restore().unwrap();
+    assert_eq!(read(a), manifest("a", "1.0"));
+    assert!(Backup::latest(a).unwrap().is_none());
+    assert_eq!(Backup::latest(b).unwrap().unwrap().dir, other.dir);
+}
+
+#[test]
+fn discarded_backups_are_gone() {
+    let [manifest_path] = &workspace("backup-discard", &["app"])[..] else {
+        unreachable!()
+    };
+
+    let backup = Backup::create(manifest_path).unwrap();
+    let dir = backup.dir.clone();
+    backup.discard().unwrap();
+    assert!(!dir.exists());
+    assert!(Backup::latest(manifest_path).unwrap().is_none());
+}
+
+#[test]
+fn undo_restores_the_latest_backup() {
+    let [manifest_path] = &workspace("backup-undo", &["app"])[..] else {
+        unreachable!()
+    };
+    let undo = UndoCommand::new(manifest_path.to_string_lossy().to_string());
+
+    assert!(matches!(undo.run(), Err(CcuError::Backup(_))));
+
+    Backup::create(manifest_path).unwrap();
+    std::fs::write(manifest_path, manifest("app", "1.0.200")).unwrap();
+    undo.run().unwrap();
+    assert_eq!(read(manifest_path), manifest("app", "1.0"));
+    assert!(matches!(undo.run(), Err(CcuError::Backup(_))));
+}
+
+#[test]
+fn atomic_writes_replace_the_file() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("write-atomic");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("Cargo.toml");
+
+    cargo_check_updates::fs::write_atomic(&path, "new file\n").unwrap();
+    assert_eq!(read(&path), "new file\n");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        cargo_check_updates::fs::write_atomic(&path, "replaced\n").unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+    cargo_check_updates::fs::write_atomic(&path, "replaced again\n").unwrap();
+    assert_eq!(read(&path), "replaced again\n");
+
+    // No temporary files are left behind, even when the rename fails
+    assert!(cargo_check_updates::fs::write_atomic(dir.join("missing/Cargo.toml"), "").is_err());
+    std::fs::create_dir(dir.join("taken")).unwrap();
+    assert!(cargo_check_updates::fs::write_atomic(dir.join("taken"), "").is_err());
+    let entries: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    assert_eq!(entries.len(), 2, "{:?}", entries);
+}
@@ -0,0 +1,48 @@
+//! Changelog extraction against a local stand-in for the registry's download endpoint.
+
+mod common;
+
+use cargo_check_updates::changelog::ReleaseNotes;
+use common::{client, download, v};
+
+#[tokio::test]
+async fn prints_sections_between_versions() {
+    let changelog = "# Changelog\n\n## 1.2.0\n\n- New API\n\n## 1.1.0\n\n- Fix\n\n## 1.0.0\n\n- First\n";
+    let client = client(vec![download("demo", "1.2.0", &[("Cargo.toml", ""), ("CHANGELOG.md", changelog)])]);
+
+    match ReleaseNotes::fetch(&client, "demo", &v("1.0.0"), &v("1.2.0")).await.unwrap() {
+        ReleaseNotes::Sections { path, sections } => {
+            assert_eq!(path, "CHANGELOG.md");
+            let texts: Vec<_> = sections.iter().map(|section| section.text.as_str()).collect();
+            assert_eq!(texts, ["## 1.2.0\n\n- New API", "## 1.1.0\n\n- Fix"]);
+        }
+        other => panic!("expected sections, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn diffs_changelogs_without_version_headings() {
+    let client = client(vec![
+        download("news", "0.1.0", &[("NEWS.md", "- old\n")]),
+        download("news", "0.2.0", &[("NEWS.md", "- new\n- old\n")]),
+    ]);
+
+    match ReleaseNotes::fetch(&client, "news", &v("0.1.0"), &v("0.2.0")).await.unwrap() {
+        ReleaseNotes::Added { path, text } => {
+            assert_eq!(path, "NEWS.md");
+            assert_eq!(text, "- new");
+        }
+        other => panic!("expected added lines, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn reports_missing_changelogs_and_downloads() {
+    let client = client(vec![download("bare", "2.0.0", &[("Cargo.toml", ""), ("src/CHANGELOG.md", "## 2.0.0\n")])]);
+
+    assert!(matches!(
+        ReleaseNotes::fetch(&client, "bare", &v("1.0.0"), &v("2.0.0")).await.unwrap(),
+        ReleaseNotes::Missing
+    ));
+    assert!(ReleaseNotes::fetch(&client, "bare", &v("1.0.0"), &v("3.0.0")).await.is_err());
+}
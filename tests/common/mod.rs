@@ -0,0 +1,80 @@
+//! Helpers shared by tests that talk to a local stand-in for the registry.
+
+use cargo_check_updates::registry::RegistryClient;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use semver::Version;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+
+pub fn v(version: &str) -> Version {
+    Version::parse(version).unwrap()
+}
+
+/// A client whose index is unreachable, serving downloads from `/crates`
+/// and registry metadata from `/api/v1`
+pub fn client(files: Vec<(String, Vec<u8>)>) -> RegistryClient {
+    let url = serve(files.into_iter().collect());
+    RegistryClient::with_index_url("http://127.0.0.1:9")
+        .with_download_url(&format!("{}/crates", url))
+        .with_api_url(&format!("{}/api/v1", url))
+}
+
+/// A `.crate` download holding the given files, with the path it's served at
+pub fn download(name: &str, version: &str, files: &[(&str, &str)]) -> (String, Vec<u8>) {
+    let path = format!("/crates/{}/{}-{}.crate", name, name, version);
+    (path, crate_archive(name, version, files))
+}
+
+/// Build a `.crate` archive holding the given files under `<name>-<version>/`
+pub fn crate_archive(name: &str, version: &str, files: &[(&str, &str)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, format!("{}-{}/{}", name, version, path), contents.as_bytes())
+            .unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Serve files by URL path over HTTP on a local port, returning the base URL
+pub fn serve(files: HashMap<String, Vec<u8>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            let request = String::from_utf8_lossy(&request);
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+            let (status, body) = match files.get(path) {
+                Some(body) => ("200 OK", body.as_slice()),
+                None => ("404 Not Found", &b""[..]),
+            };
+            let head = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            let _ = stream.write_all(head.as_bytes());
+            let _ = stream.write_all(body);
+        }
+    });
+
+    url
+}
@@ -0,0 +1,64 @@
+//! Saving a manifest that was edited on disk after `ccu` read it.
+
+use cargo_check_updates::cargo::parser::{CargoTomlParser, DependencySection};
+use cargo_check_updates::CcuError;
+use std::path::{Path, PathBuf};
+
+const MANIFEST: &str = r#"[package]
+name = "demo"
+version = "0.1.0"
+
+[dependencies]
+serde = "1.0"
+tokio = { version = "1.28", features = ["full"] }
+"#;
+
+fn manifest(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("Cargo.toml");
+    std::fs::write(&path, MANIFEST).unwrap();
+    path
+}
+
+#[test]
+fn unrelated_edits_are_kept() {
+    let path = manifest("concurrent-unrelated");
+    let mut parser = CargoTomlParser::from_path(&path).unwrap();
+    parser.update_dependency("serde", &DependencySection::Dependencies, "1.0.200").unwrap();
+
+    let edited = MANIFEST.replace("version = \"0.1.0\"", "version = \"0.2.0\"") + "log = \"0.4\"\n";
+    std::fs::write(&path, &edited).unwrap();
+
+    parser.save().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        edited.replace("serde = \"1.0\"", "serde = \"1.0.200\"")
+    );
+}
+
+#[test]
+fn conflicting_edits_are_refused() {
+    let path = manifest("concurrent-conflict");
+    let mut parser = CargoTomlParser::from_path(&path).unwrap();
+    parser.update_dependency("tokio", &DependencySection::Dependencies, "1.40.0").unwrap();
+
+    let edited = MANIFEST.replace("1.28", "1.30");
+    std::fs::write(&path, &edited).unwrap();
+
+    assert!(matches!(parser.save(), Err(CcuError::ManifestChanged { .. })));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), edited);
+}
+
+#[test]
+fn removed_dependencies_are_refused() {
+    let path = manifest("concurrent-removed");
+    let mut parser = CargoTomlParser::from_path(&path).unwrap();
+    parser.update_dependency("serde", &DependencySection::Dependencies, "1.0.200").unwrap();
+
+    let edited = MANIFEST.replace("serde = \"1.0\"\n", "");
+    std::fs::write(&path, &edited).unwrap();
+
+    assert!(matches!(parser.save(), Err(CcuError::ManifestChanged { .. })));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), edited);
+}
@@ -0,0 +1,93 @@
+use cargo_check_updates::git::remote::{tag_version, RemoteRefs};
+use semver::Version;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "ccu")
+        .env("GIT_AUTHOR_EMAIL", "ccu@example.com")
+        .env("GIT_COMMITTER_NAME", "ccu")
+        .env("GIT_COMMITTER_EMAIL", "ccu@example.com")
+        .output()
+        .expect("git should be installed");
+    assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Build a bare repo with lightweight and annotated tags, returning its path
+/// and the commit of each tag
+fn bare_repo(name: &str) -> (PathBuf, Vec<String>) {
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&root);
+    let work = root.join("work");
+    std::fs::create_dir_all(&work).unwrap();
+
+    git(&work, &["init", "-q", "-b", "main"]);
+    let mut commits = Vec::new();
+    for (message, tag_args) in [
+        ("one", vec!["tag", "v1.0.0"]),
+        ("two", vec!["tag", "-a", "v1.1.0", "-m", "release"]),
+        ("three", vec!["tag", "v2.0.0-rc.1"]),
+    ] {
+        git(&work, &["commit", "-q", "--allow-empty", "-m", message]);
+        git(&work, &tag_args);
+        commits.push(git(&work, &["rev-parse", "HEAD"]));
+    }
+    git(&work, &["tag", "serde-v3.0.0"]);
+
+    let bare = root.join("bare.git");
+    git(&root, &["clone", "-q", "--bare", "work", "bare.git"]);
+    (bare, commits)
+}
+
+#[test]
+fn lists_tags_and_branches_of_local_bare_repo() {
+    let (bare, commits) = bare_repo("lists_tags");
+    let refs = RemoteRefs::fetch(bare.to_str().unwrap()).unwrap();
+
+    assert_eq!(refs.head.as_deref(), Some(commits[2].as_str()));
+    assert_eq!(refs.branches.get("main"), Some(&commits[2]));
+    assert_eq!(refs.tags.get("v1.0.0"), Some(&commits[0]));
+    // Annotated tags resolve to the commit, not the tag object
+    assert_eq!(refs.tags.get("v1.1.0"), Some(&commits[1]));
+
+    let mut versions: Vec<_> = refs.versioned_tags("v").map(|(_, version)| version).collect();
+    versions.sort();
+    assert_eq!(
+        versions,
+        ["1.0.0", "1.1.0", "2.0.0-rc.1"].map(|v| Version::parse(v).unwrap())
+    );
+
+    assert_eq!(refs.tag_for_commit(&commits[1][..8]), Some("v1.1.0"));
+    assert_eq!(refs.tag_for_commit("0000000"), None);
+}
+
+#[test]
+fn unreachable_remote_is_an_error() {
+    let missing = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-such-repo.git");
+    assert!(RemoteRefs::fetch(missing.to_str().unwrap()).is_err());
+}
+
+#[test]
+fn tag_version_prefixes() {
+    let cases = [
+        ("v1.2.3", Some(("v", "1.2.3"))),
+        ("1.2.3", Some(("", "1.2.3"))),
+        ("serde-v1.2.3", Some(("serde-v", "1.2.3"))),
+        ("tokio-1.40.0", Some(("tokio-", "1.40.0"))),
+        ("v2.0.0-rc.1", Some(("v", "2.0.0-rc.1"))),
+        ("release/1.0.0", Some(("release/", "1.0.0"))),
+        ("foo1.2.3", None),
+        ("v1.2", None),
+        ("nightly", None),
+    ];
+
+    for (tag, expected) in cases {
+        let parsed = tag_version(tag);
+        let expected = expected.map(|(prefix, version)| (prefix, Version::parse(version).unwrap()));
+        assert_eq!(parsed, expected, "tag {:?}", tag);
+    }
+}
@@ -0,0 +1,95 @@
+use cargo_check_updates::git::Repo;
+use cargo_check_updates::ui::diff;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("git should be installed");
+    assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// A work tree with a committed Cargo.toml and an ignored target directory
+fn work_tree(name: &str) -> PathBuf {
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+
+    git(&root, &["init", "-q", "-b", "main"]);
+    git(&root, &["config", "user.name", "ccu"]);
+    git(&root, &["config", "user.email", "ccu@example.com"]);
+    std::fs::write(root.join("Cargo.toml"), "[dependencies]\nserde = \"1.0\"\n").unwrap();
+    std::fs::write(root.join("notes.txt"), "one\n").unwrap();
+    std::fs::write(root.join(".gitignore"), "target\n").unwrap();
+    git(&root, &["add", "-A"]);
+    git(&root, &["commit", "-q", "-m", "init"]);
+    root
+}
+
+#[test]
+fn detects_dirty_work_trees() {
+    let root = work_tree("repo-dirty");
+    let repo = Repo::discover(&root).unwrap();
+    assert!(!repo.is_dirty().unwrap());
+
+    // Untracked files don't count
+    std::fs::write(root.join("scratch.txt"), "").unwrap();
+    assert!(!repo.is_dirty().unwrap());
+
+    std::fs::write(root.join("notes.txt"), "two\n").unwrap();
+    assert!(repo.is_dirty().unwrap());
+}
+
+#[test]
+fn commits_only_the_given_files_on_a_new_branch() {
+    let root = work_tree("repo-commit");
+    let repo = Repo::discover(&root).unwrap();
+    assert!(repo.is_tracked(root.join("Cargo.toml")));
+    assert!(!repo.is_tracked(root.join("Cargo.lock")));
+
+    repo.create_branch("deps").unwrap();
+    std::fs::write(root.join("Cargo.toml"), "[dependencies]\nserde = \"1.0.200\"\n").unwrap();
+    std::fs::write(root.join("notes.txt"), "two\n").unwrap();
+    repo.commit(&[root.join("Cargo.toml")], "Bump serde from 1.0 to 1.0.200").unwrap();
+
+    assert_eq!(git(&root, &["branch", "--show-current"]), "deps");
+    assert_eq!(git(&root, &["log", "-1", "--format=%s"]), "Bump serde from 1.0 to 1.0.200");
+    assert_eq!(git(&root, &["show", "--name-only", "--format="]), "Cargo.toml");
+    assert!(repo.is_dirty().unwrap(), "notes.txt should be left uncommitted");
+
+    assert!(repo.create_branch("deps").is_err());
+}
+
+#[test]
+fn patches_name_files_from_the_work_tree_root() {
+    let root = work_tree("repo-patch");
+    let member = root.join("crates/app");
+    std::fs::create_dir_all(&member).unwrap();
+    let manifest = member.join("Cargo.toml");
+    std::fs::write(&manifest, "[dependencies]\nserde = \"1.0\"\n").unwrap();
+    git(&root, &["add", "-A"]);
+    git(&root, &["commit", "-q", "-m", "add app"]);
+
+    let repo = Repo::discover(&member).unwrap();
+    assert_eq!(repo.relative_path(&manifest), Some(PathBuf::from("crates/app/Cargo.toml")));
+
+    // Absolute paths and paths through .. name the same file
+    let paths = [manifest.to_string_lossy().into_owned(), format!("{}/../app/Cargo.toml", member.display())];
+    for path in paths {
+        let name = diff::patch_path(&path);
+        assert_eq!(name, "crates/app/Cargo.toml");
+
+        let patch = diff::unified_diff(
+            "[dependencies]\nserde = \"1.0\"\n",
+            "[dependencies]\nserde = \"1.0.200\"\n",
+            &name,
+        );
+        let file = root.join("upgrade.patch");
+        std::fs::write(&file, patch).unwrap();
+        git(&root, &["apply", "--check", "upgrade.patch"]);
+    }
+}
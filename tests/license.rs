@@ -0,0 +1,49 @@
+//! Reading release licenses from a local stand-in for the registry's API and downloads.
+
+mod common;
+
+use cargo_check_updates::license::License;
+use common::{client, download, v};
+
+fn metadata(name: &str, version: &str, license: Option<&str>) -> (String, Vec<u8>) {
+    let body = serde_json::json!({ "version": { "num": version, "license": license } });
+    (format!("/api/v1/crates/{}/{}", name, version), body.to_string().into_bytes())
+}
+
+fn archive(name: &str, version: &str, manifest: &str) -> (String, Vec<u8>) {
+    download(name, version, &[("Cargo.toml", manifest)])
+}
+
+#[tokio::test]
+async fn prefers_registry_metadata() {
+    // The archive disagrees, so a download would show
+    let client = client(vec![
+        metadata("demo", "1.0.0", Some("MIT/Apache-2.0")),
+        archive("demo", "1.0.0", "[package]\nname = \"demo\"\nlicense = \"BUSL-1.1\"\n"),
+    ]);
+
+    let license = License::fetch(&client, "demo", &v("1.0.0")).await.unwrap();
+    assert_eq!(license, License::spdx("MIT OR Apache-2.0"));
+}
+
+#[tokio::test]
+async fn falls_back_to_the_crate_archive() {
+    let client = client(vec![
+        // Releases with only a license file have no license in the metadata
+        metadata("demo", "1.0.0", None),
+        archive("demo", "1.0.0", "[package]\nname = \"demo\"\nlicense-file = \"LICENSE\"\n"),
+        // No metadata at all
+        archive("demo", "2.0.0", "[package]\nname = \"demo\"\nlicense = \"ISC\"\n"),
+    ]);
+
+    let license = License::fetch(&client, "demo", &v("1.0.0")).await.unwrap();
+    assert_eq!(license, License::File("LICENSE".to_string()));
+    let license = License::fetch(&client, "demo", &v("2.0.0")).await.unwrap();
+    assert_eq!(license, License::spdx("ISC"));
+}
+
+#[tokio::test]
+async fn fails_without_metadata_or_archive() {
+    let client = client(Vec::new());
+    assert!(License::fetch(&client, "demo", &v("1.0.0")).await.is_err());
+}
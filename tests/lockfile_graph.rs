@@ -0,0 +1,82 @@
+//! Walking the Cargo.lock dependency graph from a crate to what pulls it in.
+
+use cargo_check_updates::cargo::lockfile::{LockedPackage, Lockfile};
+use cargo_check_updates::duplicates;
+use cargo_check_updates::registry::client::VersionInfo;
+use semver::Version;
+use std::collections::HashMap;
+use std::path::Path;
+
+fn lockfile() -> Lockfile {
+    Lockfile::from_path(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/lockfile/Cargo.lock")).unwrap()
+}
+
+fn package<'a>(lockfile: &'a Lockfile, name: &str, version: &str) -> &'a LockedPackage {
+    lockfile
+        .packages
+        .iter()
+        .find(|package| package.name == name && package.version == version)
+        .unwrap()
+}
+
+fn names(packages: &[&LockedPackage]) -> Vec<String> {
+    packages
+        .iter()
+        .map(|package| format!("{} {}", package.name, package.version))
+        .collect()
+}
+
+#[test]
+fn resolves_versioned_entries() {
+    let lockfile = lockfile();
+    let graph = lockfile.graph();
+
+    assert_eq!(names(&graph.dependents_of(package(&lockfile, "syn", "1.0.109"))), ["darling_core 0.13.4"]);
+    assert_eq!(names(&graph.dependents_of(package(&lockfile, "syn", "2.0.60"))), ["serde_derive 1.0.200"]);
+    assert_eq!(
+        names(&graph.dependents_of(package(&lockfile, "proc-macro2", "1.0.86"))),
+        ["local-helper 0.1.0", "serde_derive 1.0.200", "syn 1.0.109", "syn 2.0.60"]
+    );
+}
+
+#[test]
+fn finds_direct_dependencies() {
+    let lockfile = lockfile();
+    let graph = lockfile.graph();
+
+    assert!(graph.is_direct(package(&lockfile, "serde", "1.0.200")));
+    // Path dependencies count as part of the workspace
+    assert!(graph.is_direct(package(&lockfile, "proc-macro2", "1.0.86")));
+    assert!(!graph.is_direct(package(&lockfile, "syn", "2.0.60")));
+
+    assert_eq!(names(&graph.direct_dependents(package(&lockfile, "syn", "1.0.109"))), ["serde_with 1.14.0"]);
+    assert_eq!(
+        names(&graph.direct_dependents(package(&lockfile, "syn", "2.0.60"))),
+        ["serde 1.0.200", "serde_with 1.14.0"]
+    );
+}
+
+#[test]
+fn pins_several_levels_down_come_through_direct_dependencies() {
+    let lockfile = lockfile();
+    let graph = lockfile.graph();
+    // syn 1 ← darling_core ← darling ← serde_with
+    let pinner = package(&lockfile, "darling_core", "0.13.4");
+
+    assert_eq!(names(&graph.direct_ancestors(pinner)), ["serde_with 1.14.0"]);
+    assert_eq!(
+        names(&graph.direct_ancestors(package(&lockfile, "serde", "1.0.200"))),
+        ["serde 1.0.200", "serde_with 1.14.0"]
+    );
+
+    let releases = [("1.14.1", "^0.13"), ("3.0.0", "^0.20")]
+        .into_iter()
+        .map(|(version, darling)| VersionInfo::with_deps(Version::parse(version).unwrap(), &[("darling", darling)]));
+    let index = HashMap::from([("serde_with".to_string(), releases.collect())]);
+    let upgrade = |target: &str| HashMap::from([("serde_with".to_string(), Version::parse(target).unwrap())]);
+
+    // Upgrading serde_with drops darling_core only once it stops requiring darling 0.13
+    assert!(duplicates::survives(&lockfile, Some("app"), &HashMap::new(), &index, pinner));
+    assert!(duplicates::survives(&lockfile, Some("app"), &upgrade("1.14.1"), &index, pinner));
+    assert!(!duplicates::survives(&lockfile, Some("app"), &upgrade("3.0.0"), &index, pinner));
+}
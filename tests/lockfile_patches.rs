@@ -0,0 +1,29 @@
+//! Reading what `[patch]` and `[replace]` entries resolved to from Cargo.lock.
+
+use cargo_check_updates::cargo::lockfile::Lockfile;
+use std::path::Path;
+
+fn lockfile() -> Lockfile {
+    Lockfile::from_path(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/patches/Cargo.lock")).unwrap()
+}
+
+#[test]
+fn patched_versions_come_from_non_registry_sources() {
+    let lockfile = lockfile();
+
+    assert_eq!(lockfile.patched_version("serde"), Some("1.0.150"));
+    assert_eq!(lockfile.patched_version("log"), Some("0.4.14"));
+    // Locked from the registry, so the patch isn't in use
+    assert_eq!(lockfile.patched_version("tokio"), None);
+    assert_eq!(lockfile.patched_version("regex"), None);
+}
+
+#[test]
+fn finds_unused_patches() {
+    let lockfile = lockfile();
+
+    let unused = lockfile.unused_patch("tokio").unwrap();
+    assert_eq!((unused.name.as_str(), unused.version.as_str()), ("tokio", "1.28.0"));
+    assert!(lockfile.unused_patch("serde").is_none());
+    assert!(lockfile.unused_patch("regex").is_none());
+}
@@ -0,0 +1,200 @@
+//! Fixture-based coverage of the dependency syntaxes Cargo accepts.
+//!
+//! Each `tests/fixtures/syntax/<name>.toml` is read, checked against the
+//! dependencies we expect to find, then every dependency is upgraded to 9.9.9
+//! and the result compared byte-for-byte with `<name>.expected.toml`.
+
+use cargo_check_updates::cargo::parser::{CargoTomlParser, DependencySection};
+use std::path::PathBuf;
+
+const FIXTURES: &[&str] = &[
+    "string",
+    "inline",
+    "table",
+    "dotted",
+    "root_dotted",
+    "inline_section",
+    "renamed",
+    "workspace",
+    "skipped",
+    "path",
+    "crlf",
+];
+
+fn fixture(file: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/syntax")
+        .join(file)
+}
+
+/// (name, requirement, section, renamed package)
+type Expected = (&'static str, &'static str, DependencySection, Option<&'static str>);
+
+fn expected_dependencies(name: &str) -> Vec<Expected> {
+    use DependencySection::*;
+
+    match name {
+        "string" => vec![
+            ("serde", "1.0", Dependencies, None),
+            ("tokio", "1", Dependencies, None),
+            ("anyhow", "^1.0.70", Dependencies, None),
+            ("log", "0.4", Dependencies, None),
+            ("regex", "~1.9", Dependencies, None),
+            ("once_cell", "=1.18.0", Dependencies, None),
+        ],
+        "inline" => vec![
+            ("serde", "1.0", Dependencies, None),
+            ("tokio", "1.28", Dependencies, None),
+            ("reqwest", "0.11", Dependencies, None),
+            ("clap", "4.0", Dependencies, None),
+        ],
+        "table" => vec![
+            ("serde", "1.0", Dependencies, None),
+            ("tokio", "1.28", Dependencies, None),
+            ("pretty_assertions", "1", DevDependencies, None),
+            ("cc", "1.0", BuildDependencies, None),
+        ],
+        "dotted" => vec![
+            ("serde", "1.0", Dependencies, None),
+            ("tokio", "1.28", Dependencies, None),
+            ("anyhow", "1.0", Dependencies, None),
+        ],
+        "root_dotted" => vec![
+            ("serde", "1.0", Dependencies, None),
+            ("tokio", "1.28", Dependencies, None),
+            ("anyhow", "1.0", DevDependencies, None),
+        ],
+        "inline_section" => vec![
+            ("serde", "1.0", Dependencies, None),
+            ("tokio", "1.28", Dependencies, None),
+        ],
+        "renamed" => vec![
+            ("serde1", "1.0", Dependencies, Some("serde")),
+            ("rand_old", "0.7", Dependencies, Some("rand")),
+            ("tokio_compat", "0.2", Dependencies, Some("tokio")),
+        ],
+        "workspace" => vec![
+            ("serde", "1.0", WorkspaceDependencies, None),
+            ("anyhow", "1.0", WorkspaceDependencies, None),
+            ("tokio", "1.28", WorkspaceDependencies, None),
+            ("clap", "4.0", WorkspaceDependencies, None),
+        ],
+        "skipped" => vec![("anyhow", "1.0", Dependencies, None)],
+        "path" => vec![
+            ("helper", "0.3", Dependencies, None),
+            ("serde", "1.0", Dependencies, None),
+            ("fixtures", "0.1", DevDependencies, None),
+        ],
+        "crlf" => vec![
+            ("serde", "1.0", Dependencies, None),
+            ("tokio", "1.28", Dependencies, None),
+        ],
+        _ => panic!("no expectations for fixture {}", name),
+    }
+}
+
+#[test]
+fn reads_every_dependency_shape() {
+    for name in FIXTURES {
+        let parser = CargoTomlParser::from_path(fixture(&format!("{}.toml", name))).unwrap();
+        let found: Vec<_> = parser
+            .get_all_dependencies()
+            .into_iter()
+            .map(|dep| (dep.name, dep.version, dep.section, dep.package))
+            .collect();
+
+        let expected: Vec<_> = expected_dependencies(name)
+            .into_iter()
+            .map(|(name, version, section, package)| {
+                (name.to_string(), version.to_string(), section, package.map(str::to_string))
+            })
+            .collect();
+
+        assert_eq!(found, expected, "dependencies in {}.toml", name);
+    }
+}
+
+#[test]
+fn unchanged_manifests_round_trip() {
+    for name in FIXTURES {
+        let path = fixture(&format!("{}.toml", name));
+        let parser = CargoTomlParser::from_path(&path).unwrap();
+        assert_eq!(
+            parser.contents(),
+            std::fs::read_to_string(&path).unwrap(),
+            "{}.toml should round-trip unchanged",
+            name
+        );
+    }
+}
+
+#[test]
+fn updates_only_touch_version_requirements() {
+    for name in FIXTURES {
+        let mut parser = CargoTomlParser::from_path(fixture(&format!("{}.toml", name))).unwrap();
+        for dep in parser.get_all_dependencies() {
+            parser.update_dependency(&dep.name, &dep.section, "9.9.9").unwrap();
+        }
+
+        let expected = std::fs::read_to_string(fixture(&format!("{}.expected.toml", name))).unwrap();
+        assert_eq!(parser.contents(), expected, "upgraded {}.toml", name);
+    }
+}
+
+#[test]
+fn updating_a_dependency_without_version_fails() {
+    let mut parser = CargoTomlParser::from_path(fixture("skipped.toml")).unwrap();
+    for name in ["serde", "tokio", "local", "remote"] {
+        assert!(
+            parser.update_dependency(name, &DependencySection::Dependencies, "9.9.9").is_err(),
+            "{} has no version to update",
+            name
+        );
+    }
+    assert!(parser
+        .update_dependency("missing", &DependencySection::Dependencies, "9.9.9")
+        .is_err());
+}
+
+#[test]
+fn reads_enabled_features() {
+    let cases: [(&str, &str, &[&str], bool); 6] = [
+        ("inline", "serde", &["derive"], true),
+        ("inline", "reqwest", &["json", "rustls-tls"], false),
+        ("inline", "clap", &[], true),
+        ("table", "serde", &["derive", "rc"], true),
+        ("table", "tokio", &["full"], true),
+        ("string", "serde", &[], true),
+    ];
+
+    for (fixture_name, name, features, default_features) in cases {
+        let parser = CargoTomlParser::from_path(fixture(&format!("{}.toml", fixture_name))).unwrap();
+        let dep = parser
+            .get_all_dependencies()
+            .into_iter()
+            .find(|dep| dep.name == name)
+            .unwrap();
+
+        assert_eq!(dep.features, features, "features of {} in {}.toml", name, fixture_name);
+        assert_eq!(dep.default_features, default_features, "default-features of {} in {}.toml", name, fixture_name);
+    }
+}
+
+#[test]
+fn versioned_path_dependencies_keep_their_path() {
+    let parser = CargoTomlParser::from_path(fixture("path.toml")).unwrap();
+    let paths: Vec<_> = parser
+        .get_all_dependencies()
+        .into_iter()
+        .map(|dep| (dep.name, dep.path))
+        .collect();
+
+    assert_eq!(
+        paths,
+        [
+            ("helper".to_string(), Some("../helper".to_string())),
+            ("serde".to_string(), None),
+            ("fixtures".to_string(), Some("../fixtures".to_string())),
+        ]
+    );
+}